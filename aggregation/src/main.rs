@@ -0,0 +1,106 @@
+//! Verifies the proofs of consecutive segments of a claim produced by the `soar` program in
+//! `ProgramOutput::Segment` mode and commits the claim's final public values.
+
+// These two lines are necessary for the program to properly compile.
+//
+// Under the hood, we wrap your main function with some extra code so that it behaves properly
+// inside the zkVM.
+#![no_main]
+sp1_zkvm::entrypoint!(main);
+
+use alloy_sol_types::SolType;
+use sha2::{Digest, Sha256};
+use soar_lib::{
+    reward_calculator::RewardCalculator,
+    state::CalculatorState,
+    types::{
+        Address, AggregationPublicValuesStruct, Bytes32, PublicValuesStruct,
+        SegmentPublicValuesStruct, TokenAmount, Zero, U256,
+    },
+};
+
+pub fn main() {
+    // Read the verifying key of the segment program and the public values of every segment
+    // proof, in order. The proofs themselves are supplied by the prover alongside the input.
+    let segment_vkey = sp1_zkvm::io::read::<[u32; 8]>();
+    let segment_public_values = sp1_zkvm::io::read::<Vec<Vec<u8>>>();
+    // The claim, only the user and the claim endpoints are used.
+    let mut reward_calculator = RewardCalculator::try_from(sp1_zkvm::io::read_vec())
+        .unwrap_or_else(|error| panic!("{}", error));
+    // The state committed by the last segment.
+    let end_state = CalculatorState::try_from(sp1_zkvm::io::read_vec())
+        .unwrap_or_else(|error| panic!("{}", error));
+
+    let claim_digest = reward_calculator.claim_digest();
+    let mut state_digest = CalculatorState::new(&mut reward_calculator)
+        .unwrap_or_else(|error| panic!("{}", error))
+        .digest();
+    for (index, public_values) in segment_public_values.iter().enumerate() {
+        let public_values_digest = Sha256::digest(public_values);
+        sp1_zkvm::lib::verify::verify_sp1_proof(&segment_vkey, &public_values_digest.into());
+
+        let segment = SegmentPublicValuesStruct::abi_decode(public_values, true).unwrap();
+        let segment_claim_digest: Bytes32 = segment.claim_digest.into();
+        let start_state_digest: Bytes32 = segment.start_state_digest.into();
+        assert!(
+            segment_claim_digest == claim_digest,
+            "segment {} proves another claim",
+            index
+        );
+        assert!(
+            start_state_digest == state_digest,
+            "segment {} does not start where the previous one ended",
+            index
+        );
+        state_digest = segment.end_state_digest.into();
+    }
+    assert!(
+        end_state.digest() == state_digest,
+        "end state does not match the last segment"
+    );
+    let token_rewards = end_state
+        .finish(&mut reward_calculator)
+        .unwrap_or_else(|error| panic!("{}", error));
+    let total_rewards = token_rewards
+        .iter()
+        .find(|(token, _)| *token == Address::default())
+        .map_or(U256::zero(), |(_, amount)| *amount);
+
+    let hashes = reward_calculator.claim.hashes();
+
+    let pub_vals = AggregationPublicValuesStruct {
+        segment_vkey: segment_vkey_bytes(&segment_vkey).into(),
+        public_values: PublicValuesStruct {
+            user: reward_calculator.user.into(),
+            total_rewards: total_rewards.into(),
+            from_reward_event_hash: hashes.from_reward_event.into(),
+            to_reward_event_hash: hashes.to_reward_event.into(),
+            from_stake_event_hash: hashes.from_stake_event.into(),
+            to_stake_event_hash: hashes.to_stake_event.into(),
+            from_user_stake_event_hash: hashes.from_user_stake_event.into(),
+            to_user_stake_event_hash: hashes.to_user_stake_event.into(),
+            updated_to_reward_event_hash: Bytes32::zero().into(),
+            token_rewards: token_rewards
+                .into_iter()
+                .map(|(token, amount)| TokenAmount {
+                    token: token.into(),
+                    amount: amount.into(),
+                })
+                .collect(),
+        },
+    };
+
+    // Commit to the public values of the program, including the segment verifying key so
+    // that verifiers can check which program proved the segments.
+    sp1_zkvm::io::commit_slice(&AggregationPublicValuesStruct::abi_encode(&pub_vals));
+}
+
+/// The words of the verifying key hash, as returned by `SP1VerifyingKey::hash_u32`, packed
+/// as 32 big endian bytes.
+fn segment_vkey_bytes(vkey: &[u32; 8]) -> Bytes32 {
+    let mut bytes = Bytes32::zero();
+    for (chunk, word) in bytes.chunks_mut(4).zip(vkey) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    bytes
+}
@@ -11,9 +11,13 @@ sp1_zkvm::entrypoint!(main);
 use alloy_sol_types::{sol_data::Bytes, SolType};
 //use fibonacci_lib::{fibonacci, PublicValuesStruct};
 use soar_lib::{
+    merkle::RewardTree,
     reward,
     reward_calculator::RewardCalculator,
-    types::{Bytes32, PublicValuesStruct, Zero},
+    state::CalculatorState,
+    types::{
+        ProgramOutput, PublicValuesStruct, RewardRootPublicValuesStruct, SegmentPublicValuesStruct,
+    },
 };
 
 pub fn main() {
@@ -21,52 +25,73 @@ pub fn main() {
     //
     // Behind the scenes, this compiles down to a custom system call which handles reading inputs
     // from the prover.
+    let output = sp1_zkvm::io::read::<ProgramOutput>();
+    // The calculator input is JSON or the cheaper to parse packed encoding of
+    // `soar_lib::packed`.
     let data = sp1_zkvm::io::read_vec();
-    let mut reward_calculator = RewardCalculator::from(data);
+    let reward_calculator =
+        RewardCalculator::try_from(data).unwrap_or_else(|error| panic!("{}", error));
 
-    // Compute the n'th fibonacci number using a function from the workspace lib crate.
-    let total_rewards = reward_calculator.calculate_reward();
-    let mut claim = reward_calculator.claim;
+    let bytes = match output {
+        ProgramOutput::UserReward => user_reward(reward_calculator),
+        ProgramOutput::RewardRoot => reward_root(reward_calculator),
+        ProgramOutput::Segment => segment(reward_calculator),
+    };
 
-    let mut from_user_stake_event = claim.from_user_stake_event;
-    let mut from_user_stake_event_hash = Bytes32::zero();
-    if from_user_stake_event.is_some() {
-        from_user_stake_event_hash = from_user_stake_event.unwrap().hash();
-    }
-    let mut from_stake_event_hash = Bytes32::zero();
-    let mut from_stake_event = claim.from_stake_event;
-    if from_stake_event.is_some() {
-        from_stake_event_hash = from_stake_event.unwrap().hash();
-    }
-    let mut from_reward_event_hash = Bytes32::zero();
-    let mut from_reward_event = claim.from_reward_event;
-    if from_reward_event.is_some() {
-        from_reward_event_hash = from_reward_event.unwrap().hash();
-    }
+    // Commit to the public values of the program. The final proof will have a commitment to all the
+    // bytes that were committed to.
+    sp1_zkvm::io::commit_slice(&bytes);
+}
+
+/// Calculates the claim user's reward and encodes it as `PublicValuesStruct`.
+fn user_reward(mut reward_calculator: RewardCalculator) -> Vec<u8> {
+    // Compute the user's rewards using a function from the workspace lib crate.
+    let pub_vals = reward_calculator
+        .public_values()
+        .unwrap_or_else(|error| panic!("{}", error));
+
+    // Encode the public values of the program.
+    PublicValuesStruct::abi_encode(&pub_vals)
+}
 
-    let mut to_user_stake_event = claim.to_user_stake_event;
-    let mut to_user_stake_event_hash = Bytes32::zero();
-    if to_user_stake_event.is_some() {
-        to_user_stake_event_hash = to_user_stake_event.unwrap().hash();
-    }
+/// Calculates the reward of every staker of the segment and encodes the Merkle root over
+/// them as `RewardRootPublicValuesStruct`.
+fn reward_root(mut reward_calculator: RewardCalculator) -> Vec<u8> {
+    let tree = RewardTree::from_calculator(&mut reward_calculator)
+        .unwrap_or_else(|error| panic!("{}", error));
+    let total_rewards = tree
+        .total_reward()
+        .unwrap_or_else(|error| panic!("{}", error));
+    let hashes = reward_calculator.claim.hashes();
 
-    // Encode the output of the program.
-    let pub_vals = PublicValuesStruct {
-        user: reward_calculator.user.into(),
+    let pub_vals = RewardRootPublicValuesStruct {
+        reward_root: tree.root().into(),
         total_rewards: total_rewards.into(),
-        from_reward_event_hash: from_reward_event_hash.into(),
-        to_reward_event_hash: claim.to_reward_event.hash().into(),
-        from_stake_event_hash: from_stake_event_hash.into(),
-        to_stake_event_hash: claim.to_stake_event.hash().into(),
-        from_user_stake_event_hash: from_user_stake_event_hash.into(),
-        to_user_stake_event_hash: to_user_stake_event_hash.into(),
-        updated_to_reward_event_hash: Bytes32::zero().into(),
+        from_reward_event_hash: hashes.from_reward_event.into(),
+        to_reward_event_hash: hashes.to_reward_event.into(),
+        from_stake_event_hash: hashes.from_stake_event.into(),
+        to_stake_event_hash: hashes.to_stake_event.into(),
     };
 
-    // Encode the public values of the program.
-    let bytes = PublicValuesStruct::abi_encode(&pub_vals);
+    RewardRootPublicValuesStruct::abi_encode(&pub_vals)
+}
 
-    // Commit to the public values of the program. The final proof will have a commitment to all the
-    // bytes that were committed to.
-    sp1_zkvm::io::commit_slice(&bytes);
+/// Applies the input's events to the state read after it and encodes the digests of the
+/// state before and after as `SegmentPublicValuesStruct`. The aggregation program checks
+/// that the segments chain together from the start to the end of the claim.
+fn segment(mut reward_calculator: RewardCalculator) -> Vec<u8> {
+    let mut state = CalculatorState::try_from(sp1_zkvm::io::read_vec())
+        .unwrap_or_else(|error| panic!("{}", error));
+    let start_state_digest = state.digest();
+    state
+        .advance(&mut reward_calculator, None)
+        .unwrap_or_else(|error| panic!("{}", error));
+
+    let pub_vals = SegmentPublicValuesStruct {
+        claim_digest: reward_calculator.claim_digest().into(),
+        start_state_digest: start_state_digest.into(),
+        end_state_digest: state.digest().into(),
+    };
+
+    SegmentPublicValuesStruct::abi_encode(&pub_vals)
 }
@@ -0,0 +1,231 @@
+//! Proves a claim in segments of at most `--segment-reward-events` reward events with the
+//! `soar` program and aggregates the segment proofs with the `soar-aggregation` program.
+//!
+//! You can run this script using the following command:
+//! ```shell
+//! RUST_LOG=info cargo run --release --bin aggregate -- --execute --input-file ../data/input.json
+//! ```
+//! or
+//! ```shell
+//! RUST_LOG=info cargo run --release --bin aggregate -- --prove --input-file ../data/input.json
+//! ```
+
+use alloy_sol_types::SolType;
+use clap::Parser;
+use serde_json::Value;
+use soar_lib::reward_calculator::RewardCalculator;
+use soar_lib::state::CalculatorState;
+use soar_lib::types::{AggregationPublicValuesStruct, ProgramOutput, SegmentPublicValuesStruct};
+use sp1_sdk::{include_elf, HashableKey, ProverClient, SP1Proof, SP1Stdin};
+use std::fs;
+
+/// The ELF of the program proving the segments.
+pub const SOAR_REWARDS: &[u8] = include_elf!("soar");
+
+/// The ELF of the program aggregating the segment proofs.
+pub const SOAR_AGGREGATION: &[u8] = include_elf!("soar-aggregation");
+
+/// The arguments for the aggregate command.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct AggregateArgs {
+    #[clap(long)]
+    execute: bool,
+
+    #[clap(long)]
+    prove: bool,
+
+    #[clap(long)]
+    input_file: String,
+
+    #[clap(long, default_value = "1000")]
+    segment_reward_events: usize,
+}
+
+/// The input of a segment starting at `state`: the claim with the next `stake_events` stake
+/// events and the next `reward_events` reward events.
+fn segment_input(
+    input: &Value,
+    state: &CalculatorState,
+    stake_events: usize,
+    reward_events: usize,
+) -> Value {
+    let next = |events: &str, index: usize, count: usize| {
+        let remaining = &input[events].as_array().unwrap()[index..];
+        Value::Array(remaining[..count.min(remaining.len())].to_vec())
+    };
+    let mut segment = input.clone();
+    segment["stake_events"] = next("stake_events", state.stake_index, stake_events);
+    segment["reward_events"] = next("reward_events", state.reward_index, reward_events);
+    segment
+}
+
+fn main() {
+    // Setup the logger.
+    sp1_sdk::utils::setup_logger();
+
+    // Parse the command line arguments.
+    let args = AggregateArgs::parse();
+
+    if args.execute == args.prove {
+        eprintln!("Error: You must specify either --execute or --prove");
+        std::process::exit(1);
+    }
+    if args.segment_reward_events == 0 {
+        eprintln!("Error: --segment-reward-events must be positive");
+        std::process::exit(1);
+    }
+
+    let data = fs::read(&args.input_file).unwrap_or_else(|error| {
+        eprintln!("Error: cannot read {}: {}", args.input_file, error);
+        std::process::exit(1);
+    });
+    // The input is either JSON or packed, so the segments are cut from the decoded input.
+    let mut calculator = RewardCalculator::try_from(data).unwrap_or_else(|error| {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    });
+    // Reject an input that does not match its claim before splitting it, the segments could
+    // not reach the end of the claimed chains otherwise.
+    calculator.verify().unwrap_or_else(|error| {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    });
+    let input = serde_json::to_value(&calculator).unwrap();
+    let stake_events = calculator.stake_events.len();
+    let reward_events = calculator.reward_events.len();
+
+    // Split the claim into segments, running them on the host to get the state each one
+    // starts from.
+    let mut state = CalculatorState::new(&mut calculator).unwrap_or_else(|error| {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    });
+    let mut segments = Vec::new();
+    loop {
+        let start_state = state.clone();
+        let segment = segment_input(&input, &start_state, usize::MAX, args.segment_reward_events);
+        let mut segment_calculator =
+            RewardCalculator::try_from(serde_json::to_vec(&segment).unwrap()).unwrap();
+        state
+            .advance(&mut segment_calculator, None)
+            .unwrap_or_else(|error| {
+                eprintln!("Error: {}", error);
+                std::process::exit(1);
+            });
+        let applied_stake_events = state.stake_index - start_state.stake_index;
+        if applied_stake_events == 0 && state.reward_index == start_state.reward_index {
+            eprintln!(
+                "Error: segment {} applies no events at stake event {} and reward event {}",
+                segments.len(),
+                state.stake_index,
+                state.reward_index
+            );
+            std::process::exit(1);
+        }
+        // Only supply the stake events the segment applies and the next one, which its last
+        // reward event is compared against.
+        let segment = segment_input(
+            &input,
+            &start_state,
+            applied_stake_events + 1,
+            args.segment_reward_events,
+        );
+        segments.push((segment, start_state));
+        if state.reward_index == reward_events && state.stake_index == stake_events {
+            break;
+        }
+    }
+    let token_rewards = state.finish(&mut calculator).unwrap_or_else(|error| {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    });
+    println!("Segments: {}", segments.len());
+    for (token, amount) in &token_rewards {
+        println!("Rewards of 0x{}: {}", hex::encode(token), amount);
+    }
+
+    // Setup the prover client.
+    let client = ProverClient::new();
+    let segment_stdin = |segment: &Value, start_state: &CalculatorState| {
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&ProgramOutput::Segment);
+        stdin.write_vec(serde_json::to_vec(segment).unwrap());
+        stdin.write_vec(serde_json::to_vec(start_state).unwrap());
+        stdin
+    };
+
+    if args.execute {
+        // Execute every segment and check that their digests chain together.
+        let mut state_digest = CalculatorState::new(&mut calculator).unwrap().digest();
+        for (index, (segment, start_state)) in segments.iter().enumerate() {
+            let (output, report) = client
+                .execute(SOAR_REWARDS, segment_stdin(segment, start_state))
+                .run()
+                .unwrap();
+            let decoded = SegmentPublicValuesStruct::abi_decode(output.as_slice(), true).unwrap();
+            assert_eq!(decoded.start_state_digest.0, state_digest);
+            state_digest = decoded.end_state_digest.0;
+            println!(
+                "Segment {}: {} cycles",
+                index,
+                report.total_instruction_count()
+            );
+        }
+        assert_eq!(state_digest, state.digest());
+        println!("Segments chain together.");
+    } else {
+        let (segment_pk, segment_vk) = client.setup(SOAR_REWARDS);
+        let (aggregation_pk, aggregation_vk) = client.setup(SOAR_AGGREGATION);
+
+        // Prove every segment in compressed form, so it can be verified in the zkVM.
+        let mut proofs = Vec::new();
+        for (index, (segment, start_state)) in segments.iter().enumerate() {
+            let proof = client
+                .prove(&segment_pk, segment_stdin(segment, start_state))
+                .compressed()
+                .run()
+                .expect("failed to generate segment proof");
+            println!("Proved segment {}", index);
+            proofs.push(proof);
+        }
+
+        // The aggregation only needs the claim of the input.
+        let mut claim = input.clone();
+        claim["stake_events"] = Value::Array(Vec::new());
+        claim["reward_events"] = Value::Array(Vec::new());
+
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&segment_vk.hash_u32());
+        stdin.write(
+            &proofs
+                .iter()
+                .map(|proof| proof.public_values.to_vec())
+                .collect::<Vec<_>>(),
+        );
+        stdin.write_vec(serde_json::to_vec(&claim).unwrap());
+        stdin.write_vec(serde_json::to_vec(&state).unwrap());
+        for proof in proofs {
+            match proof.proof {
+                SP1Proof::Compressed(proof) => stdin.write_proof(*proof, segment_vk.vk.clone()),
+                _ => panic!("segment proof is not compressed"),
+            }
+        }
+
+        let proof = client
+            .prove(&aggregation_pk, stdin)
+            .run()
+            .expect("failed to generate aggregation proof");
+        println!("Successfully generated aggregation proof!");
+
+        client
+            .verify(&proof, &aggregation_vk)
+            .expect("failed to verify proof");
+        println!("Successfully verified proof!");
+
+        let decoded =
+            AggregationPublicValuesStruct::abi_decode(proof.public_values.as_slice(), true)
+                .unwrap();
+        println!("Total Rewards: {:?}", decoded.public_values.total_rewards);
+    }
+}
@@ -0,0 +1,61 @@
+//! Writes every reward event of a claim with the stakes it was shared out with and the user's
+//! share of it, in the shape of the `userRewardEvents` of `output.json` or as CSV.
+//!
+//! You can run this script using the following command:
+//! ```shell
+//! cargo run --release --bin breakdown -- --input-file ../data/input.json --format csv \
+//!     --output-file breakdown.csv
+//! ```
+
+use clap::{Parser, ValueEnum};
+use soar_lib::breakdown::RewardBreakdown;
+use soar_lib::reward_calculator::RewardCalculator;
+use std::fs;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Format {
+    Json,
+    Csv,
+}
+
+/// The arguments for the breakdown command.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct BreakdownArgs {
+    #[clap(long)]
+    input_file: String,
+
+    #[clap(long, value_enum, default_value = "json")]
+    format: Format,
+
+    #[clap(long)]
+    output_file: String,
+}
+
+fn main() {
+    let args = BreakdownArgs::parse();
+
+    let input = fs::read(&args.input_file).unwrap_or_else(|error| {
+        eprintln!("Error: cannot read {}: {}", args.input_file, error);
+        std::process::exit(1);
+    });
+    let breakdown = RewardCalculator::try_from(input)
+        .and_then(|mut calculator| RewardBreakdown::from_calculator(&mut calculator))
+        .unwrap_or_else(|error| {
+            eprintln!("Error: {}", error);
+            std::process::exit(1);
+        });
+    let output = match args.format {
+        Format::Json => breakdown.to_json(),
+        Format::Csv => breakdown.to_csv().into_bytes(),
+    };
+    fs::write(&args.output_file, output).unwrap_or_else(|error| {
+        eprintln!("Error: cannot write {}: {}", args.output_file, error);
+        std::process::exit(1);
+    });
+    println!(
+        "Wrote {} reward events to {}",
+        breakdown.events.len(),
+        args.output_file
+    );
+}
@@ -0,0 +1,123 @@
+//! Assembles the calculator input of a claim from complete dumps of the stake and reward
+//! chains, selecting each end of the segments by event hash or by timestamp.
+//!
+//! You can run this script using the following command:
+//! ```shell
+//! cargo run --release --bin build-input -- --stakes ../data/stakes.json \
+//!     --rewards ../data/rewards.json --user 0x90F79bf6EB2c4f870365E785982E1f101E93b906 \
+//!     --from-stake-hash 0x86be... --to-stake-timestamp 1734271047 \
+//!     --from-reward-hash 0x... --to-reward-timestamp 1734271047 --output-file input.json
+//! ```
+
+use clap::Parser;
+use soar_lib::error::SoarError;
+use soar_lib::input::{EventSelector, InputBuilder, SegmentSelection};
+use soar_lib::types::{parse_bytes, parse_u256};
+use std::fs;
+
+/// The arguments for the build-input command.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct BuildInputArgs {
+    /// Every stake chain event, as written to `stakes.json`.
+    #[clap(long)]
+    stakes: String,
+
+    /// Every reward chain event, as written to `rewards.json`.
+    #[clap(long)]
+    rewards: String,
+
+    #[clap(long)]
+    user: String,
+
+    /// Last stake event before the segment. The segment starts at the beginning of the
+    /// stake chain if neither the hash nor the timestamp is given.
+    #[clap(long, conflicts_with = "from_stake_timestamp")]
+    from_stake_hash: Option<String>,
+
+    #[clap(long)]
+    from_stake_timestamp: Option<String>,
+
+    /// Last stake event of the segment.
+    #[clap(
+        long,
+        required_unless_present = "to_stake_timestamp",
+        conflicts_with = "to_stake_timestamp"
+    )]
+    to_stake_hash: Option<String>,
+
+    #[clap(long)]
+    to_stake_timestamp: Option<String>,
+
+    /// Last reward event before the segment. The segment starts at the beginning of the
+    /// reward chain if neither the hash nor the timestamp is given.
+    #[clap(long, conflicts_with = "from_reward_timestamp")]
+    from_reward_hash: Option<String>,
+
+    #[clap(long)]
+    from_reward_timestamp: Option<String>,
+
+    /// Last reward event of the segment.
+    #[clap(
+        long,
+        required_unless_present = "to_reward_timestamp",
+        conflicts_with = "to_reward_timestamp"
+    )]
+    to_reward_hash: Option<String>,
+
+    #[clap(long)]
+    to_reward_timestamp: Option<String>,
+
+    #[clap(long)]
+    output_file: String,
+}
+
+fn selector(
+    hash: &Option<String>,
+    timestamp: &Option<String>,
+) -> Result<Option<EventSelector>, SoarError> {
+    match (hash, timestamp) {
+        (Some(hash), _) => Ok(Some(EventSelector::Hash(parse_bytes("hash", hash)?))),
+        (None, Some(timestamp)) => Ok(Some(EventSelector::Timestamp(parse_u256(
+            "timestamp",
+            timestamp,
+        )?))),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Reads the file at `path`, exiting with an error naming it if it cannot be read.
+fn read(path: &str) -> Vec<u8> {
+    fs::read(path).unwrap_or_else(|error| {
+        eprintln!("Error: cannot read {}: {}", path, error);
+        std::process::exit(1);
+    })
+}
+
+fn build(args: &BuildInputArgs, stakes: &[u8], rewards: &[u8]) -> Result<Vec<u8>, SoarError> {
+    let builder = InputBuilder::from_json(stakes, rewards)?;
+    let selection = SegmentSelection {
+        from_stake: selector(&args.from_stake_hash, &args.from_stake_timestamp)?,
+        to_stake: selector(&args.to_stake_hash, &args.to_stake_timestamp)?.unwrap(),
+        from_reward: selector(&args.from_reward_hash, &args.from_reward_timestamp)?,
+        to_reward: selector(&args.to_reward_hash, &args.to_reward_timestamp)?.unwrap(),
+    };
+    let calculator = builder.build(parse_bytes("user", &args.user)?, selection)?;
+    Ok(serde_json::to_vec(&calculator)?)
+}
+
+fn main() {
+    let args = BuildInputArgs::parse();
+
+    let stakes = read(&args.stakes);
+    let rewards = read(&args.rewards);
+    let input = build(&args, &stakes, &rewards).unwrap_or_else(|error| {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    });
+    fs::write(&args.output_file, input).unwrap_or_else(|error| {
+        eprintln!("Error: cannot write {}: {}", args.output_file, error);
+        std::process::exit(1);
+    });
+    println!("Wrote calculator input to {}", args.output_file);
+}
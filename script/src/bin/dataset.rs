@@ -0,0 +1,121 @@
+//! Generates `stakes.json`, `rewards.json`, `input.json` and `output.json` from a seed,
+//! simulating the stake and reward chain contracts. The same arguments always give the same
+//! files.
+//!
+//! You can run this script using the following command:
+//! ```shell
+//! cargo run --release --bin dataset -- --seed 42 --output-dir ../data/seed-42
+//! ```
+//! Existing files are only replaced with `--force`, so the committed `../data` files are not
+//! overwritten by accident.
+
+use clap::Parser;
+use soar_lib::dataset::{generate, DatasetParams};
+use std::fs;
+use std::path::Path;
+
+/// The arguments for the dataset command. Unset parameters take the values of
+/// `DatasetParams::default`.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct DatasetArgs {
+    #[clap(long)]
+    seed: u64,
+
+    #[clap(long)]
+    users: Option<usize>,
+
+    #[clap(long)]
+    iterations: Option<usize>,
+
+    /// Relative weight of staking at each iteration.
+    #[clap(long)]
+    stake_probability: Option<f64>,
+
+    /// Relative weight of unstaking at each iteration.
+    #[clap(long)]
+    unstake_probability: Option<f64>,
+
+    /// Relative weight of adding rewards at each iteration.
+    #[clap(long)]
+    reward_probability: Option<f64>,
+
+    /// Least number of seconds passing before each iteration.
+    #[clap(long)]
+    min_time_increase: Option<u64>,
+
+    /// Most number of seconds passing before each iteration.
+    #[clap(long)]
+    max_time_increase: Option<u64>,
+
+    /// Directory the files are written to, created if missing.
+    #[clap(long)]
+    output_dir: String,
+
+    /// Replace files already in the output directory.
+    #[clap(long)]
+    force: bool,
+}
+
+fn main() {
+    let args = DatasetArgs::parse();
+
+    let defaults = DatasetParams::default();
+    let params = DatasetParams {
+        seed: args.seed,
+        users: args.users.unwrap_or(defaults.users),
+        iterations: args.iterations.unwrap_or(defaults.iterations),
+        stake_probability: args.stake_probability.unwrap_or(defaults.stake_probability),
+        unstake_probability: args
+            .unstake_probability
+            .unwrap_or(defaults.unstake_probability),
+        reward_probability: args
+            .reward_probability
+            .unwrap_or(defaults.reward_probability),
+        min_time_increase: args.min_time_increase.unwrap_or(defaults.min_time_increase),
+        max_time_increase: args.max_time_increase.unwrap_or(defaults.max_time_increase),
+        ..defaults
+    };
+    let dataset = generate(&params).unwrap_or_else(|error| {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    });
+
+    let dir = Path::new(&args.output_dir);
+    let files = [
+        ("stakes.json", dataset.stakes_json()),
+        ("rewards.json", dataset.rewards_json()),
+        ("input.json", dataset.input_json()),
+        ("output.json", dataset.output_json()),
+    ];
+    if !args.force {
+        if let Some((name, _)) = files.iter().find(|(name, _)| dir.join(name).exists()) {
+            eprintln!(
+                "Error: {} already exists, use --force to replace it",
+                dir.join(name).display()
+            );
+            std::process::exit(1);
+        }
+    }
+    fs::create_dir_all(dir).unwrap_or_else(|error| {
+        eprintln!("Error: cannot create {}: {}", dir.display(), error);
+        std::process::exit(1);
+    });
+    for (name, contents) in files {
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap_or_else(|error| {
+            eprintln!("Error: cannot write {}: {}", path.display(), error);
+            std::process::exit(1);
+        });
+    }
+    println!(
+        "Generated {} stake and {} reward events in {}",
+        dataset.stakes.events.len(),
+        dataset.rewards.events.len(),
+        args.output_dir
+    );
+    println!(
+        "Claim of {} for {} rewards",
+        dataset.output.user, dataset.output.total_rewards
+    );
+}
@@ -9,10 +9,19 @@
 //! ```shell
 //! RUST_LOG=info cargo run --release -- --prove
 //! ```
+//! Add `--packed` to send the input in the compact binary encoding instead of JSON;
+//! `--execute` reports the cycles of both encodings.
+//!
+//! To check the zkVM output against the calculator running on the host,
+//! ```shell
+//! RUST_LOG=info cargo run --release -- --native
+//! ```
 
 use alloy_sol_types::SolType;
 use clap::Parser;
-use soar_lib::types::PublicValuesStruct;
+use soar_lib::packed::encode_input;
+use soar_lib::reward_calculator::RewardCalculator;
+use soar_lib::types::{ProgramOutput, PublicValuesStruct, RewardRootPublicValuesStruct};
 use sp1_sdk::{include_elf, ProverClient, SP1Stdin};
 use std::fs;
 use std::io;
@@ -30,8 +39,28 @@ struct Args {
     #[clap(long)]
     prove: bool,
 
+    /// Run the calculator on the host and the program in the zkVM and compare their output.
+    #[clap(long)]
+    native: bool,
+
     #[clap(long)]
     input_file: String,
+
+    /// Commit the Merkle root of every staker's reward instead of the claim user's reward.
+    #[clap(long)]
+    reward_root: bool,
+
+    /// Send the input to the program in the packed binary encoding instead of JSON.
+    #[clap(long)]
+    packed: bool,
+}
+
+/// The program's stdin for an input in either encoding.
+fn program_stdin(program_output: &ProgramOutput, input: Vec<u8>) -> SP1Stdin {
+    let mut stdin = SP1Stdin::new();
+    stdin.write(program_output);
+    stdin.write_vec(input);
+    stdin
 }
 
 fn main() {
@@ -41,8 +70,17 @@ fn main() {
     // Parse the command line arguments.
     let args = Args::parse();
 
-    if args.execute == args.prove {
-        eprintln!("Error: You must specify either --execute or --prove");
+    if [args.execute, args.prove, args.native]
+        .iter()
+        .filter(|mode| **mode)
+        .count()
+        != 1
+    {
+        eprintln!("Error: You must specify either --execute, --prove or --native");
+        std::process::exit(1);
+    }
+    if args.native && args.reward_root {
+        eprintln!("Error: --native only compares the claim user's reward");
         std::process::exit(1);
     }
 
@@ -50,7 +88,6 @@ fn main() {
     let client = ProverClient::new();
 
     // Setup the inputs.
-    let mut stdin = SP1Stdin::new();
     /*
      * Write the public input values here.
      * user address,
@@ -59,21 +96,99 @@ fn main() {
      *
      * The program should output the total reward the given user can claim.
      */
-    let stake_data = fs::read(args.input_file).unwrap();
-    stdin.write_vec(stake_data);
+    let program_output = if args.reward_root {
+        ProgramOutput::RewardRoot
+    } else {
+        ProgramOutput::UserReward
+    };
+    let input_data = fs::read(args.input_file).unwrap();
+    let mut calculator = RewardCalculator::try_from(input_data).unwrap_or_else(|error| {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    });
+    let json_input = serde_json::to_vec(&calculator).unwrap();
+    let packed_input = encode_input(&calculator);
+    let stdin = if args.packed {
+        program_stdin(&program_output, packed_input.clone())
+    } else {
+        program_stdin(&program_output, json_input.clone())
+    };
+
+    if args.native {
+        // Calculate the public values on the host.
+        let native = calculator.public_values().unwrap_or_else(|error| {
+            eprintln!("Error: native calculation failed: {}", error);
+            std::process::exit(1);
+        });
+        println!("Native Total Rewards: {:?}", native.total_rewards);
+
+        // Execute the program and compare its output.
+        let (output, report) = client.execute(SOAR_REWARDS, stdin).run().unwrap();
+        println!("Number of cycles: {}", report.total_instruction_count());
+        let zkvm = PublicValuesStruct::abi_decode(output.as_slice(), true).unwrap();
 
-    if args.execute {
+        let mismatches = diff_public_values(&native, &zkvm);
+        if !mismatches.is_empty() {
+            for mismatch in &mismatches {
+                eprintln!("Mismatch: {}", mismatch);
+            }
+            std::process::exit(1);
+        }
+        println!("Native and zkVM public values match.");
+    } else if args.execute {
         // Execute the program
         let (output, report) = client.execute(SOAR_REWARDS, stdin).run().unwrap();
         println!("Program executed successfully.");
 
         // Read the output.
-        let decoded = PublicValuesStruct::abi_decode(output.as_slice(), true).unwrap();
-        println!("Total Rewards: {:?}", decoded.total_rewards);
-        println!("To Reward Event Hash: {:?}", decoded.to_reward_event_hash);
+        if args.reward_root {
+            let decoded =
+                RewardRootPublicValuesStruct::abi_decode(output.as_slice(), true).unwrap();
+            println!("Reward Root: {:?}", decoded.reward_root);
+            println!("Total Rewards: {:?}", decoded.total_rewards);
+            println!("To Reward Event Hash: {:?}", decoded.to_reward_event_hash);
+        } else {
+            let decoded = PublicValuesStruct::abi_decode(output.as_slice(), true).unwrap();
+            println!("Total Rewards: {:?}", decoded.total_rewards);
+            for token_reward in &decoded.token_rewards {
+                println!(
+                    "Token {:?} Rewards: {:?}",
+                    token_reward.token, token_reward.amount
+                );
+            }
+            println!("To Reward Event Hash: {:?}", decoded.to_reward_event_hash);
+        }
 
         // Record the number of cycles executed.
         println!("Number of cycles: {}", report.total_instruction_count());
+
+        // Compare with the cycles of the other input encoding.
+        let other_input = if args.packed {
+            json_input
+        } else {
+            packed_input
+        };
+        let (_, other_report) = client
+            .execute(SOAR_REWARDS, program_stdin(&program_output, other_input))
+            .run()
+            .unwrap();
+        let (json_cycles, packed_cycles) = if args.packed {
+            (
+                other_report.total_instruction_count(),
+                report.total_instruction_count(),
+            )
+        } else {
+            (
+                report.total_instruction_count(),
+                other_report.total_instruction_count(),
+            )
+        };
+        println!("JSON input cycles: {}", json_cycles);
+        println!(
+            "Packed input cycles: {} ({:.1}% of JSON)",
+            packed_cycles,
+            packed_cycles as f64 * 100.0 / json_cycles as f64
+        );
     } else {
         // Setup the program for proving.
         let (pk, vk) = client.setup(SOAR_REWARDS);
@@ -93,3 +208,54 @@ fn main() {
         println!("Successfully verified proof!");
     }
 }
+
+/// Describes every field in which the native and the zkVM public values differ.
+fn diff_public_values(native: &PublicValuesStruct, zkvm: &PublicValuesStruct) -> Vec<String> {
+    let mut mismatches = Vec::new();
+    macro_rules! compare {
+        ($($field:ident),*) => {
+            $(
+                if native.$field != zkvm.$field {
+                    mismatches.push(format!(
+                        "{}: native {:?}, zkVM {:?}",
+                        stringify!($field),
+                        native.$field,
+                        zkvm.$field
+                    ));
+                }
+            )*
+        };
+    }
+    compare!(
+        user,
+        total_rewards,
+        from_reward_event_hash,
+        to_reward_event_hash,
+        from_stake_event_hash,
+        to_stake_event_hash,
+        from_user_stake_event_hash,
+        to_user_stake_event_hash,
+        updated_to_reward_event_hash
+    );
+    if native.token_rewards.len() != zkvm.token_rewards.len() {
+        mismatches.push(format!(
+            "token_rewards: native has {} tokens, zkVM {}",
+            native.token_rewards.len(),
+            zkvm.token_rewards.len()
+        ));
+    }
+    for (index, (native, zkvm)) in native
+        .token_rewards
+        .iter()
+        .zip(&zkvm.token_rewards)
+        .enumerate()
+    {
+        if native.token != zkvm.token || native.amount != zkvm.amount {
+            mismatches.push(format!(
+                "token_rewards[{}]: native {:?} {:?}, zkVM {:?} {:?}",
+                index, native.token, native.amount, zkvm.token, zkvm.amount
+            ));
+        }
+    }
+    mismatches
+}
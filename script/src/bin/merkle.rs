@@ -0,0 +1,75 @@
+//! Builds the Merkle tree over every staker's reward for a calculator input and exports the
+//! root along with each staker's proof, matching the root committed by the program with
+//! `--reward-root`.
+//!
+//! You can run this script using the following command:
+//! ```shell
+//! cargo run --release --bin merkle -- --input-file ../data/input.json --output-file proofs.json
+//! ```
+
+use clap::Parser;
+use serde_json::{json, Map, Value};
+use soar_lib::merkle::RewardTree;
+use soar_lib::reward_calculator::RewardCalculator;
+use soar_lib::types::hex_string;
+use std::fs;
+
+/// The arguments for the merkle command.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct MerkleArgs {
+    #[clap(long)]
+    input_file: String,
+
+    #[clap(long)]
+    output_file: String,
+}
+
+fn main() {
+    let args = MerkleArgs::parse();
+
+    let data = fs::read(&args.input_file).unwrap_or_else(|error| {
+        eprintln!("Error: cannot read {}: {}", args.input_file, error);
+        std::process::exit(1);
+    });
+    let mut calculator = RewardCalculator::try_from(data).unwrap_or_else(|error| {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    });
+    let tree = RewardTree::from_calculator(&mut calculator).unwrap_or_else(|error| {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    });
+
+    let mut claims = Map::new();
+    for (user, reward) in tree.rewards() {
+        let proof = tree.proof(user).unwrap();
+        claims.insert(
+            hex_string(user),
+            json!({
+                "reward": reward.to_string(),
+                "proof": proof.proof.iter().map(|node| hex_string(node)).collect::<Vec<_>>(),
+            }),
+        );
+    }
+    let output = json!({
+        "root": hex_string(&tree.root()),
+        "totalRewards": tree.total_reward().unwrap().to_string(),
+        "claims": Value::Object(claims),
+    });
+
+    fs::write(
+        &args.output_file,
+        serde_json::to_string_pretty(&output).unwrap(),
+    )
+    .unwrap_or_else(|error| {
+        eprintln!("Error: cannot write {}: {}", args.output_file, error);
+        std::process::exit(1);
+    });
+    println!("Reward root: {}", hex_string(&tree.root()));
+    println!(
+        "Wrote {} claims to {}",
+        tree.rewards().len(),
+        args.output_file
+    );
+}
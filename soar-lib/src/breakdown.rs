@@ -0,0 +1,118 @@
+//! Per reward event breakdown of a user's reward, to show which reward events contributed
+//! how much to a claim.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::SoarError;
+use crate::reward::{RewardChainExtendedEvent, RewardChainExtendedEventJSON};
+use crate::reward_calculator::RewardCalculator;
+use crate::rounding::{RoundingPolicy, PRECISION};
+use crate::types::{hex_string, U256, U512};
+
+/// The user's share of a single reward event of the claim.
+#[derive(Clone, Debug)]
+pub struct RewardEventShare {
+    pub reward_event: RewardChainExtendedEvent,
+    /// The user's stake and the total stake the event was shared out with.
+    pub user_stake: U256,
+    pub total_stake: U256,
+    /// The user's share in sub-units, `PRECISION` sub-units making one base unit of the token.
+    pub share: U512,
+}
+
+/// A reward event with the stakes it was shared out with, in the shape of the
+/// `userRewardEvents` of `output.json`. `userShare` is in base units of the reward token,
+/// with the sub-units as 18 decimals.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserRewardEvent {
+    pub reward_event: RewardChainExtendedEventJSON,
+    pub user_stake: String,
+    pub total_staked: String,
+    #[serde(default)]
+    pub user_share: String,
+}
+
+/// Header of the CSV export, one row per reward event.
+pub const CSV_HEADER: &str =
+    "rewardEventHash,timestamp,token,amount,totalRewards,userStake,totalStaked,userShare";
+
+/// Formats sub-units as base units with 18 decimals.
+pub fn format_share(share: U512) -> String {
+    let (units, fraction) = share.div_mod(U512::from(PRECISION));
+    format!("{}.{:0>18}", units, fraction.to_string())
+}
+
+impl RewardEventShare {
+    pub fn user_reward_event(&self) -> UserRewardEvent {
+        UserRewardEvent {
+            reward_event: (&self.reward_event).into(),
+            user_stake: self.user_stake.to_string(),
+            total_staked: self.total_stake.to_string(),
+            user_share: format_share(self.share),
+        }
+    }
+}
+
+/// Every reward event of a claim with the user's share of it.
+#[derive(Clone, Debug)]
+pub struct RewardBreakdown {
+    pub events: Vec<RewardEventShare>,
+}
+
+impl RewardBreakdown {
+    /// Verifies the input and shares out every reward event of the claim. The shares are
+    /// not rounded; the user's reward is their sum once each is floored to whole units.
+    pub fn from_calculator(calculator: &mut RewardCalculator) -> Result<Self, SoarError> {
+        let distribution = calculator.calculate_distribution(RoundingPolicy::default())?;
+        let events = calculator
+            .reward_events
+            .iter()
+            .zip(distribution.events)
+            .map(|(event, rounding)| RewardEventShare {
+                reward_event: event.clone(),
+                user_stake: rounding.user_stake,
+                total_stake: rounding.total_stake,
+                share: rounding.share,
+            })
+            .collect();
+        Ok(Self { events })
+    }
+
+    pub fn user_reward_events(&self) -> Vec<UserRewardEvent> {
+        self.events
+            .iter()
+            .map(RewardEventShare::user_reward_event)
+            .collect()
+    }
+
+    /// The breakdown as the JSON array of `userRewardEvents`.
+    pub fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec_pretty(&self.user_reward_events()).unwrap()
+    }
+
+    /// The breakdown as CSV with the columns of `CSV_HEADER`. The token column is empty for
+    /// the reward chain's own token.
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("{}\n", CSV_HEADER);
+        for event in &self.events {
+            let reward = &event.reward_event;
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{}\n",
+                hex_string(&reward.current_event_hash),
+                reward.timestamp,
+                reward
+                    .token
+                    .as_ref()
+                    .map(|token| hex_string(token))
+                    .unwrap_or_default(),
+                reward.amount,
+                reward.total_reward,
+                event.user_stake,
+                event.total_stake,
+                format_share(event.share)
+            ));
+        }
+        csv
+    }
+}
@@ -0,0 +1,420 @@
+//! Seeded generation of stake and reward chains with a claim over them, using the contract
+//! models of `simulator`. The same parameters always give the same dataset.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::breakdown::{format_share, RewardBreakdown, UserRewardEvent};
+use crate::error::{Chain, SoarError};
+use crate::input::{EventSelector, InputBuilder, SegmentSelection};
+use crate::reward::RewardChainExtendedEventJSON;
+use crate::reward_calculator::{RewardCalculator, RewardClaimParameters};
+use crate::rounding::{Rounder, RoundingPolicy, PRECISION};
+use crate::simulator::{RewardChain, StakeChain, StakeSnapshot};
+use crate::stake::{StakeChainExtendedEvent, StakeChainExtendedEventJSON};
+use crate::types::{hex_string, keccak256, mul_div_scaled, Address, Bytes32, Zero, U256, U512};
+
+/// Parameters of `generate`. Amounts are in whole tokens of 18 decimals and are drawn with
+/// two decimals.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DatasetParams {
+    pub seed: u64,
+    pub users: usize,
+    pub iterations: usize,
+    /// Relative weights of staking, unstaking and adding rewards at each iteration.
+    pub stake_probability: f64,
+    pub unstake_probability: f64,
+    pub reward_probability: f64,
+    /// Bounds of the time passing before each iteration, in seconds.
+    pub min_time_increase: u64,
+    pub max_time_increase: u64,
+    pub start_timestamp: u64,
+    /// Tokens every user holds before staking.
+    pub balance: u64,
+    pub max_stake: u64,
+    pub max_reward: u64,
+}
+
+impl DatasetParams {
+    /// Checks that the parameters describe contract calls that can be simulated.
+    pub fn validate(&self) -> Result<(), SoarError> {
+        if self.users == 0 {
+            return Err(SoarError::InvalidDatasetParams("no users"));
+        }
+        let probabilities = [
+            self.stake_probability,
+            self.unstake_probability,
+            self.reward_probability,
+        ];
+        // NaN is not non-negative either.
+        if !probabilities.iter().all(|probability| *probability >= 0.0)
+            || probabilities.iter().sum::<f64>() == 0.0
+        {
+            return Err(SoarError::InvalidDatasetParams(
+                "probabilities must be non-negative and not all zero",
+            ));
+        }
+        // Every call is mined in a block of its own, as in the hardhat network.
+        if self.min_time_increase == 0 {
+            return Err(SoarError::InvalidDatasetParams(
+                "the time increase must be at least one second",
+            ));
+        }
+        if self.min_time_increase > self.max_time_increase {
+            return Err(SoarError::InvalidDatasetParams(
+                "the minimum time increase exceeds the maximum",
+            ));
+        }
+        if self.max_stake == 0 || self.max_reward == 0 {
+            return Err(SoarError::InvalidDatasetParams(
+                "stake and reward amounts must be at least one token",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for DatasetParams {
+    /// The parameters `new-dataset.ts` used.
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            users: 19,
+            iterations: 100,
+            stake_probability: 0.335,
+            unstake_probability: 0.335,
+            reward_probability: 0.33,
+            min_time_increase: 1,
+            max_time_increase: 86_400,
+            start_timestamp: 1_732_800_000,
+            balance: 1_000,
+            max_stake: 10,
+            max_reward: 100,
+        }
+    }
+}
+
+/// The expected result of the claim, as written to `output.json`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpectedOutput {
+    pub user: String,
+    pub total_rewards: String,
+    pub from_reward_chain_hash: String,
+    pub from_reward_chain_timestamp: String,
+    pub to_reward_chain_hash: String,
+    pub to_reward_chain_timestamp: String,
+    pub from_stake_chain_hash: String,
+    #[serde(rename = "fromStakeChainTimeStamp")]
+    pub from_stake_chain_timestamp: String,
+    pub to_stake_chain_hash: String,
+    pub to_stake_chain_timestamp: String,
+    pub from_user_stake_chain: String,
+    pub from_user_stake_chain_timestamp: String,
+    pub to_user_stake_chain: String,
+    pub to_user_stake_chain_timestamp: String,
+    pub user_reward_events: Vec<UserRewardEvent>,
+}
+
+impl ExpectedOutput {
+    /// The result the calculator gives for the claim of `calculator`, to compare with an
+    /// expected one.
+    pub fn from_calculator(calculator: &mut RewardCalculator) -> Result<Self, SoarError> {
+        let user_reward_events = RewardBreakdown::from_calculator(calculator)?.user_reward_events();
+        let total_rewards = calculator.calculate_reward()?;
+        Ok(Self::new(
+            &calculator.user,
+            &calculator.claim,
+            total_rewards,
+            user_reward_events,
+        ))
+    }
+
+    fn new(
+        user: &Address,
+        claim: &RewardClaimParameters,
+        total_rewards: U256,
+        user_reward_events: Vec<UserRewardEvent>,
+    ) -> Self {
+        let (from_reward_chain_hash, from_reward_chain_timestamp) = hash_and_timestamp(
+            claim
+                .from_reward_event
+                .as_ref()
+                .map(|event| (event.current_event_hash, event.timestamp)),
+        );
+        let (to_reward_chain_hash, to_reward_chain_timestamp) = hash_and_timestamp(Some((
+            claim.to_reward_event.current_event_hash,
+            claim.to_reward_event.timestamp,
+        )));
+        let (from_stake_chain_hash, from_stake_chain_timestamp) =
+            stake_endpoint(&claim.from_stake_event);
+        let (to_stake_chain_hash, to_stake_chain_timestamp) =
+            stake_endpoint(&Some(claim.to_stake_event.clone()));
+        let (from_user_stake_chain, from_user_stake_chain_timestamp) =
+            stake_endpoint(&claim.from_user_stake_event);
+        let (to_user_stake_chain, to_user_stake_chain_timestamp) =
+            stake_endpoint(&claim.to_user_stake_event);
+        ExpectedOutput {
+            user: hex_string(user),
+            total_rewards: total_rewards.to_string(),
+            from_reward_chain_hash,
+            from_reward_chain_timestamp,
+            to_reward_chain_hash,
+            to_reward_chain_timestamp,
+            from_stake_chain_hash,
+            from_stake_chain_timestamp,
+            to_stake_chain_hash,
+            to_stake_chain_timestamp,
+            from_user_stake_chain,
+            from_user_stake_chain_timestamp,
+            to_user_stake_chain,
+            to_user_stake_chain_timestamp,
+            user_reward_events,
+        }
+    }
+}
+
+/// A generated dataset: both chains in full, a claim over them and its expected result.
+#[derive(Clone, Debug)]
+pub struct Dataset {
+    pub stakes: StakeChain,
+    pub rewards: RewardChain,
+    pub calculator: RewardCalculator,
+    pub output: ExpectedOutput,
+}
+
+/// SplitMix64, small and stable across platforms and versions.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in `[low, high]`.
+    fn range(&mut self, low: u64, high: u64) -> u64 {
+        low + self.next_u64() % (high - low + 1)
+    }
+
+    /// A positive amount of at most `max` tokens with two decimals.
+    fn amount(&mut self, max: u64) -> U256 {
+        U256::from(self.range(1, max * 100)) * U256::from(PRECISION / 100)
+    }
+}
+
+/// Address of the `index`th generated user.
+pub fn user_address(index: usize) -> Address {
+    let hash = keccak256(format!("soar user {}", index).as_bytes());
+    hash[12..].try_into().unwrap()
+}
+
+fn hash_and_timestamp(event: Option<(Bytes32, U256)>) -> (String, String) {
+    let (hash, timestamp) = event.unwrap_or((Bytes32::zero(), U256::zero()));
+    (hex_string(&hash), timestamp.to_string())
+}
+
+fn stake_endpoint(event: &Option<StakeChainExtendedEvent>) -> (String, String) {
+    hash_and_timestamp(
+        event
+            .as_ref()
+            .map(|event| (event.current_event_hash, event.timestamp)),
+    )
+}
+
+/// The total stake and every user's stake in the contract when a reward event is added.
+struct RewardStakes {
+    total_staked: U256,
+    stakes: BTreeMap<Address, StakeSnapshot>,
+}
+
+/// The expected result of the claim, from the stakes held in the simulated contract when
+/// each reward event of the claimed segment was added rather than from the calculator
+/// input. Each share is floored as `new-dataset.ts` does.
+fn expected_output(
+    user: &Address,
+    claim: &RewardClaimParameters,
+    rewards: &RewardChain,
+    reward_stakes: &[RewardStakes],
+) -> Result<ExpectedOutput, SoarError> {
+    let position = |hash: Bytes32| {
+        rewards
+            .events
+            .iter()
+            .position(|event| event.current_event_hash == hash)
+            .ok_or(SoarError::UnknownEvent {
+                chain: Chain::Reward,
+                hash,
+            })
+    };
+    let start = match &claim.from_reward_event {
+        Some(event) => position(event.current_event_hash)? + 1,
+        None => 0,
+    };
+    let end = position(claim.to_reward_event.current_event_hash)? + 1;
+
+    let mut rounder = Rounder::new(RoundingPolicy::FloorPerEvent);
+    let mut user_reward_events = Vec::with_capacity(end - start);
+    for (event, stakes) in rewards.events[start..end]
+        .iter()
+        .zip(&reward_stakes[start..end])
+    {
+        let user_stake = stakes
+            .stakes
+            .get(user)
+            .map_or(U256::zero(), |snapshot| snapshot.total_stake);
+        let share = if stakes.total_staked.is_zero() {
+            U512::zero()
+        } else {
+            mul_div_scaled(
+                user_stake,
+                event.amount,
+                U256::from(PRECISION),
+                stakes.total_staked,
+            )?
+        };
+        rounder.push(
+            event.current_event_hash,
+            user_stake,
+            stakes.total_staked,
+            share,
+        )?;
+        user_reward_events.push(UserRewardEvent {
+            reward_event: event.into(),
+            user_stake: user_stake.to_string(),
+            total_staked: stakes.total_staked.to_string(),
+            user_share: format_share(share),
+        });
+    }
+    let total_rewards = rounder.finish()?.total_reward;
+    Ok(ExpectedOutput::new(
+        user,
+        claim,
+        total_rewards,
+        user_reward_events,
+    ))
+}
+
+/// Simulates `params.iterations` random contract calls and picks a claim of one staker
+/// over random segments of the resulting chains.
+pub fn generate(params: &DatasetParams) -> Result<Dataset, SoarError> {
+    params.validate()?;
+    let mut rng = Rng(params.seed);
+    let users: Vec<Address> = (0..params.users).map(user_address).collect();
+    let balance = U256::from(params.balance) * U256::from(PRECISION);
+    let mut stakes = StakeChain::new();
+    let mut rewards = RewardChain::new();
+    let mut timestamp = params.start_timestamp;
+    let mut reward_stakes = Vec::new();
+
+    let total_weight =
+        params.stake_probability + params.unstake_probability + params.reward_probability;
+    for _ in 0..params.iterations {
+        timestamp += rng.range(params.min_time_increase, params.max_time_increase);
+        let now = U256::from(timestamp);
+        let action = rng.next_f64() * total_weight;
+        if action < params.stake_probability + params.unstake_probability
+            || stakes.total_staked.is_zero()
+        {
+            let user = users[rng.range(0, users.len() as u64 - 1) as usize];
+            let staked = stakes.stake_snapshot(&user).0;
+            let amount = rng.amount(params.max_stake);
+            // Unstake only what is staked, and stake only what is left of the balance.
+            if action >= params.stake_probability && !staked.is_zero() {
+                stakes.unstake(user, amount.min(staked), now)?;
+            } else if staked + amount <= balance {
+                stakes.stake(user, amount, now)?;
+            } else {
+                stakes.unstake(user, amount.min(staked), now)?;
+            }
+        } else {
+            rewards.add_rewards(rng.amount(params.max_reward), now)?;
+            reward_stakes.push(RewardStakes {
+                total_staked: stakes.total_staked,
+                stakes: stakes.stakes.clone(),
+            });
+        }
+    }
+    if stakes.events.is_empty() || rewards.events.is_empty() {
+        return Err(SoarError::InvalidDatasetParams(
+            "not enough iterations for a claim",
+        ));
+    }
+
+    // Both segments end at a random time in the second half of the chains, after the first
+    // event of either chain, and span at least half of the time before it.
+    let first = stakes.events[0].timestamp.max(rewards.events[0].timestamp);
+    let middle = params.start_timestamp + (timestamp - params.start_timestamp) / 2;
+    let to = rng.range(first.as_u64().max(middle), timestamp);
+    let from = rng.range(
+        params.start_timestamp,
+        params.start_timestamp + (to - params.start_timestamp) / 2,
+    );
+    let user_index = rng.range(0, users.len() as u64 - 1) as usize;
+    // Prefer a user who staked before the end of the segments.
+    let user = (0..users.len())
+        .map(|offset| users[(user_index + offset) % users.len()])
+        .find(|user| {
+            stakes
+                .events
+                .iter()
+                .any(|event| event.user == *user && event.timestamp <= U256::from(to))
+        })
+        .unwrap_or(users[user_index]);
+
+    // A segment starts at the beginning of its chain if no event precedes `from`.
+    let from_selector = |first: U256| {
+        (first <= U256::from(from)).then_some(EventSelector::Timestamp(U256::from(from)))
+    };
+    let selection = SegmentSelection {
+        from_stake: from_selector(stakes.events[0].timestamp),
+        to_stake: EventSelector::Timestamp(U256::from(to)),
+        from_reward: from_selector(rewards.events[0].timestamp),
+        to_reward: EventSelector::Timestamp(U256::from(to)),
+    };
+    let calculator =
+        InputBuilder::new(stakes.events.clone(), rewards.events.clone()).build(user, selection)?;
+    let output = expected_output(&user, &calculator.claim, &rewards, &reward_stakes)?;
+
+    Ok(Dataset {
+        stakes,
+        rewards,
+        calculator,
+        output,
+    })
+}
+
+impl Dataset {
+    /// Every stake event, as written to `stakes.json`.
+    pub fn stakes_json(&self) -> Vec<u8> {
+        let events: Vec<StakeChainExtendedEventJSON> =
+            self.stakes.events.iter().map(Into::into).collect();
+        serde_json::to_vec_pretty(&events).unwrap()
+    }
+
+    /// Every reward event, as written to `rewards.json`.
+    pub fn rewards_json(&self) -> Vec<u8> {
+        let events: Vec<RewardChainExtendedEventJSON> =
+            self.rewards.events.iter().map(Into::into).collect();
+        serde_json::to_vec_pretty(&events).unwrap()
+    }
+
+    /// The calculator input of the claim, as written to `input.json`.
+    pub fn input_json(&self) -> Vec<u8> {
+        serde_json::to_vec(&self.calculator).unwrap()
+    }
+
+    /// The expected result of the claim, as written to `output.json`.
+    pub fn output_json(&self) -> Vec<u8> {
+        serde_json::to_vec_pretty(&self.output).unwrap()
+    }
+}
@@ -0,0 +1,214 @@
+use crate::error::SoarError;
+use crate::reward::RewardChainExtendedEvent;
+use crate::reward_calculator::RewardCalculator;
+use crate::rounding::{RewardDistribution, Rounder, RoundingPolicy, PRECISION};
+use crate::stake::StakeChainExtendedEvent;
+use crate::types::{mul_div_scaled, Address, U256, U512};
+
+/// An algorithm computing a user's reward from a verified `RewardCalculator`.
+///
+/// Engines are only handed calculators whose stake and reward segments have already been
+/// checked against the claim, see `RewardCalculator::calculate_reward_with`.
+pub trait RewardEngine {
+    fn user_reward(&self, calculator: &RewardCalculator) -> Result<U256, SoarError>;
+}
+
+/// Replays every stake event up to each reward event and credits the user's share of it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReplayEngine {
+    pub policy: RoundingPolicy,
+}
+
+impl ReplayEngine {
+    pub fn distribution(
+        &self,
+        calculator: &RewardCalculator,
+    ) -> Result<RewardDistribution, SoarError> {
+        self.replay(calculator, calculator.reward_events.iter())
+    }
+
+    /// The distribution over the reward events of `token` only, see
+    /// `RewardChainExtendedEvent::reward_token`.
+    pub fn token_distribution(
+        &self,
+        calculator: &RewardCalculator,
+        token: &Address,
+    ) -> Result<RewardDistribution, SoarError> {
+        self.replay(
+            calculator,
+            calculator
+                .reward_events
+                .iter()
+                .filter(|event| event.reward_token() == *token),
+        )
+    }
+
+    fn replay<'a>(
+        &self,
+        calculator: &RewardCalculator,
+        reward_events: impl Iterator<Item = &'a RewardChainExtendedEvent>,
+    ) -> Result<RewardDistribution, SoarError> {
+        let mut total_user_stake = calculator.initial_user_stake();
+        let mut total_stake = calculator.initial_total_stake();
+        let mut stake_event_index = 0; // Stake index
+        let mut rounder = Rounder::new(self.policy);
+        let precision = U256::from(PRECISION);
+        for reward_event in reward_events {
+            while stake_event_index < calculator.stake_events.len()
+                && reward_event.timestamp > calculator.stake_events[stake_event_index].timestamp
+            {
+                let stake_event = &calculator.stake_events[stake_event_index];
+                if stake_event.user == calculator.user {
+                    total_user_stake = stake_event.total_user_stake;
+                }
+                total_stake = stake_event.total_staked;
+                stake_event_index += 1;
+            }
+            // Calculate the reward for the user at this point.
+            let share = if total_stake.is_zero() {
+                U512::zero()
+            } else {
+                mul_div_scaled(
+                    total_user_stake,
+                    reward_event.amount,
+                    precision,
+                    total_stake,
+                )?
+            };
+            rounder.push(
+                reward_event.current_event_hash,
+                total_user_stake,
+                total_stake,
+                share,
+            )?;
+        }
+        rounder.finish()
+    }
+}
+
+impl RewardEngine for ReplayEngine {
+    fn user_reward(&self, calculator: &RewardCalculator) -> Result<U256, SoarError> {
+        Ok(self.distribution(calculator)?.total_reward)
+    }
+}
+
+/// Computes rewards from an index of the segment shared by every user, see `RewardIndex`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AccumulatorEngine;
+
+impl RewardEngine for AccumulatorEngine {
+    fn user_reward(&self, calculator: &RewardCalculator) -> Result<U256, SoarError> {
+        RewardIndex::build(
+            calculator.initial_total_stake(),
+            &calculator.stake_events,
+            &calculator.reward_events,
+        )?
+        .user_reward(
+            &calculator.user,
+            calculator.initial_user_stake(),
+            &calculator.stake_events,
+        )
+    }
+}
+
+/// Sub-units per stake unit of the index, `PRECISION` squared.
+const INDEX_SCALE: u128 = PRECISION as u128 * PRECISION as u128;
+
+/// Cumulative reward per stake unit over the reward events of a stake and reward segment.
+///
+/// The index only depends on the global chains, so it can be built once and shared by the
+/// claims of every user over the same segment. A user's reward is settled at their own stake
+/// events only, as their stake times the difference of the index across each of their stake
+/// intervals, and floored once for the whole claim.
+///
+/// Each reward event adds `amount * INDEX_SCALE / total_stake` floored to the index, so the
+/// reward is the exact reward floored, less the fractions dropped by the index, at most
+/// `stake * reward events / INDEX_SCALE` units. `ReplayEngine` with `RoundingPolicy::FloorAtEnd`
+/// drops at most `reward events / PRECISION` units, so as long as the stake times the number
+/// of reward events stays below `INDEX_SCALE`, the engines differ by at most one unit. With
+/// `RoundingPolicy::FloorPerEvent`, the replay may pay up to one unit less per reward event.
+///
+/// As in the replay, a stake event only applies to reward events with a later timestamp.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RewardIndex {
+    /// Index before each reward event, followed by the index after the last one.
+    index: Vec<U512>,
+    /// Number of reward events shared out before each stake event takes effect.
+    checkpoints: Vec<usize>,
+}
+
+impl RewardIndex {
+    pub fn build(
+        initial_total_stake: U256,
+        stake_events: &[StakeChainExtendedEvent],
+        reward_events: &[RewardChainExtendedEvent],
+    ) -> Result<Self, SoarError> {
+        let mut total_stakes = Vec::with_capacity(reward_events.len());
+        let mut checkpoints = Vec::with_capacity(stake_events.len());
+        let mut total_stake = initial_total_stake;
+        for stake_event in stake_events {
+            while total_stakes.len() < reward_events.len()
+                && reward_events[total_stakes.len()].timestamp <= stake_event.timestamp
+            {
+                total_stakes.push(total_stake);
+            }
+            checkpoints.push(total_stakes.len());
+            total_stake = stake_event.total_staked;
+        }
+        total_stakes.resize(reward_events.len(), total_stake);
+
+        let scale = U256::from(INDEX_SCALE);
+        let mut index = Vec::with_capacity(reward_events.len() + 1);
+        let mut reward_per_stake = U512::zero();
+        index.push(reward_per_stake);
+        for (reward_event, total_stake) in reward_events.iter().zip(total_stakes) {
+            if !total_stake.is_zero() {
+                let increase =
+                    mul_div_scaled(reward_event.amount, U256::one(), scale, total_stake)?;
+                reward_per_stake = reward_per_stake
+                    .checked_add(increase)
+                    .ok_or(SoarError::Overflow)?;
+            }
+            index.push(reward_per_stake);
+        }
+        Ok(Self { index, checkpoints })
+    }
+
+    /// Reward of `user` starting with `initial_user_stake` over the segment the index was
+    /// built from. `stake_events` must be the same events.
+    pub fn user_reward(
+        &self,
+        user: &Address,
+        initial_user_stake: U256,
+        stake_events: &[StakeChainExtendedEvent],
+    ) -> Result<U256, SoarError> {
+        let mut accrued = U512::zero();
+        let mut user_stake = initial_user_stake;
+        let mut since = 0;
+        for (stake_event, checkpoint) in stake_events.iter().zip(&self.checkpoints) {
+            if stake_event.user != *user {
+                continue;
+            }
+            accrued = self.accrue(accrued, user_stake, since, *checkpoint)?;
+            user_stake = stake_event.total_user_stake;
+            since = *checkpoint;
+        }
+        accrued = self.accrue(accrued, user_stake, since, self.index.len() - 1)?;
+        U256::try_from(accrued / U512::from(INDEX_SCALE))
+    }
+
+    /// Adds the reward of `user_stake` over the reward events from `start` up to `end`
+    /// (exclusive) to `accrued`.
+    fn accrue(
+        &self,
+        accrued: U512,
+        user_stake: U256,
+        start: usize,
+        end: usize,
+    ) -> Result<U512, SoarError> {
+        U512::from(user_stake)
+            .checked_mul(self.index[end] - self.index[start])
+            .and_then(|reward| accrued.checked_add(reward))
+            .ok_or(SoarError::Overflow)
+    }
+}
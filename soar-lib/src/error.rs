@@ -0,0 +1,244 @@
+use std::fmt;
+
+use crate::types::{Address, Bytes32, U256};
+
+/// The hash chain an error refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chain {
+    Stake,
+    Reward,
+    UserStake,
+    StakeHistory,
+}
+
+impl fmt::Display for Chain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chain::Stake => write!(f, "stake"),
+            Chain::Reward => write!(f, "reward"),
+            Chain::UserStake => write!(f, "user stake"),
+            Chain::StakeHistory => write!(f, "stake history"),
+        }
+    }
+}
+
+/// Which end of a claimed chain segment an endpoint error refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endpoint {
+    From,
+    To,
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Endpoint::From => write!(f, "from"),
+            Endpoint::To => write!(f, "to"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SoarError {
+    /// A decimal or hex field could not be parsed.
+    Parse { field: &'static str, value: String },
+    /// The calculator input is not valid JSON for the expected shape.
+    Json(String),
+    /// A log is not one of the decoded contract events or does not decode as one.
+    InvalidLog { log_index: u64, message: String },
+    /// A packed calculator input is truncated or malformed at the given byte offset.
+    PackedInput { offset: usize },
+    /// The supplied current hash of an event does not match its recomputed hash.
+    HashMismatch {
+        chain: Chain,
+        index: usize,
+        supplied: Bytes32,
+        computed: Bytes32,
+    },
+    /// An event does not reference the hash of the event before it.
+    ChainLink {
+        chain: Chain,
+        index: usize,
+        expected: Bytes32,
+        actual: Bytes32,
+    },
+    /// A segment does not start or end at the event committed in the claim.
+    EndpointMismatch {
+        chain: Chain,
+        endpoint: Endpoint,
+        expected: Bytes32,
+        actual: Bytes32,
+    },
+    /// An event timestamp is earlier than the one of the event before it.
+    NonMonotonicTimestamp {
+        chain: Chain,
+        index: usize,
+        previous: U256,
+        timestamp: U256,
+    },
+    /// A user stake snapshot belongs to a different user.
+    UserMismatch { expected: Address, actual: Address },
+    /// A user stake snapshot lies outside of the claimed stake segment.
+    UserSnapshotOutOfRange { endpoint: Endpoint },
+    /// A user's stake event does not follow from the user's previous stake.
+    UserStakeMismatch {
+        index: usize,
+        expected: Option<U256>,
+        actual: U256,
+    },
+    /// No event with the requested hash was found among the supplied events.
+    UnknownEvent { chain: Chain, hash: Bytes32 },
+    /// No event was found at or before the requested timestamp.
+    NoEventAt { chain: Chain, timestamp: U256 },
+    /// The requested end of a segment lies before its start.
+    ReversedSegment { chain: Chain },
+    /// The stakes of the stakers in the stake history do not add up to the total stake at
+    /// the start of the stake segment.
+    SnapshotTotalMismatch { expected: U256, actual: U256 },
+    /// A reward event can not be applied because the supplied stake events end before its
+    /// timestamp while the stake segment continues.
+    StakeEventsExhausted { index: usize },
+    /// A reward event is not later than the start of the stake segment, so the total stake
+    /// it is shared out with is not known from the segment.
+    RewardBeforeStakeSegment { index: usize },
+    /// A single reward was requested for reward events distributing more than one token.
+    MultipleRewardTokens,
+    /// A simulated contract call reverted with the given reason.
+    Reverted(&'static str),
+    /// Dataset parameters that can not produce a claim, with the reason.
+    InvalidDatasetParams(&'static str),
+    /// An intermediate value of the reward calculation does not fit in 256 bits.
+    Overflow,
+    /// A division by a zero total.
+    DivisionByZero,
+}
+
+impl fmt::Display for SoarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SoarError::Parse { field, value } => {
+                write!(f, "invalid value for {}: {:?}", field, value)
+            }
+            SoarError::Json(message) => write!(f, "invalid input: {}", message),
+            SoarError::InvalidLog { log_index, message } => {
+                write!(f, "invalid log {}: {}", log_index, message)
+            }
+            SoarError::PackedInput { offset } => {
+                write!(f, "invalid packed input at byte {}", offset)
+            }
+            SoarError::HashMismatch {
+                chain,
+                index,
+                supplied,
+                computed,
+            } => write!(
+                f,
+                "{} event {} has hash 0x{} but 0x{} was supplied",
+                chain,
+                index,
+                hex::encode(computed),
+                hex::encode(supplied)
+            ),
+            SoarError::ChainLink {
+                chain,
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} event {} links to 0x{} instead of 0x{}",
+                chain,
+                index,
+                hex::encode(actual),
+                hex::encode(expected)
+            ),
+            SoarError::EndpointMismatch {
+                chain,
+                endpoint,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} segment {} endpoint is 0x{} instead of the claimed 0x{}",
+                chain,
+                endpoint,
+                hex::encode(actual),
+                hex::encode(expected)
+            ),
+            SoarError::NonMonotonicTimestamp {
+                chain,
+                index,
+                previous,
+                timestamp,
+            } => write!(
+                f,
+                "{} event {} has timestamp {} before the previous {}",
+                chain, index, timestamp, previous
+            ),
+            SoarError::UserMismatch { expected, actual } => write!(
+                f,
+                "user stake event belongs to 0x{} instead of 0x{}",
+                hex::encode(actual),
+                hex::encode(expected)
+            ),
+            SoarError::UserSnapshotOutOfRange { endpoint } => write!(
+                f,
+                "{} user stake event lies outside of the stake segment",
+                endpoint
+            ),
+            SoarError::UserStakeMismatch {
+                index,
+                expected,
+                actual,
+            } => match expected {
+                Some(expected) => write!(
+                    f,
+                    "stake event {} sets the user stake to {} instead of {}",
+                    index, actual, expected
+                ),
+                None => write!(f, "stake event {} moves the user stake out of range", index),
+            },
+            SoarError::UnknownEvent { chain, hash } => {
+                write!(f, "no {} event with hash 0x{}", chain, hex::encode(hash))
+            }
+            SoarError::NoEventAt { chain, timestamp } => {
+                write!(f, "no {} event at or before timestamp {}", chain, timestamp)
+            }
+            SoarError::ReversedSegment { chain } => {
+                write!(f, "{} segment ends before it starts", chain)
+            }
+            SoarError::SnapshotTotalMismatch { expected, actual } => write!(
+                f,
+                "stake history adds up to {} instead of the total stake {}",
+                actual, expected
+            ),
+            SoarError::StakeEventsExhausted { index } => write!(
+                f,
+                "reward event {} needs stake events beyond the ones supplied",
+                index
+            ),
+            SoarError::RewardBeforeStakeSegment { index } => write!(
+                f,
+                "reward event {} lies before the start of the stake segment",
+                index
+            ),
+            SoarError::MultipleRewardTokens => {
+                write!(f, "reward events distribute more than one token")
+            }
+            SoarError::Reverted(reason) => write!(f, "transaction reverted: {}", reason),
+            SoarError::InvalidDatasetParams(reason) => {
+                write!(f, "invalid dataset parameters: {}", reason)
+            }
+            SoarError::Overflow => write!(f, "arithmetic overflow"),
+            SoarError::DivisionByZero => write!(f, "division by zero"),
+        }
+    }
+}
+
+impl std::error::Error for SoarError {}
+
+impl From<serde_json::Error> for SoarError {
+    fn from(error: serde_json::Error) -> Self {
+        SoarError::Json(error.to_string())
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+
+use crate::error::{Chain, SoarError};
+use crate::reward::RewardChainExtendedEvent;
+use crate::reward_calculator::{RewardCalculator, RewardClaimParameters};
+use crate::stake::StakeChainExtendedEvent;
+use crate::types::{Address, Bytes32, Zero, U256};
+
+/// Selects a snapshot event of a chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventSelector {
+    /// The event with this current hash. The zero hash is the start of the chain.
+    Hash(Bytes32),
+    /// The latest event at or before this timestamp.
+    Timestamp(U256),
+}
+
+/// The snapshots delimiting the segments of a claim. Missing `from` snapshots mean the
+/// segment starts at the beginning of the chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SegmentSelection {
+    pub from_stake: Option<EventSelector>,
+    pub to_stake: EventSelector,
+    pub from_reward: Option<EventSelector>,
+    pub to_reward: EventSelector,
+}
+
+/// Assembles `RewardCalculator` inputs from complete dumps of the stake and reward chains,
+/// as written to `stakes.json` and `rewards.json`.
+#[derive(Clone, Debug)]
+pub struct InputBuilder {
+    pub stake_events: Vec<StakeChainExtendedEvent>,
+    pub reward_events: Vec<RewardChainExtendedEvent>,
+}
+
+/// Position of the event picked by `selector`, `None` for the start of the chain.
+fn position(
+    chain: Chain,
+    selector: EventSelector,
+    events: impl DoubleEndedIterator<Item = (Bytes32, U256)> + ExactSizeIterator,
+) -> Result<Option<usize>, SoarError> {
+    match selector {
+        EventSelector::Hash(hash) if hash == Bytes32::zero() => Ok(None),
+        EventSelector::Hash(hash) => events
+            .enumerate()
+            .find(|(_, (event_hash, _))| *event_hash == hash)
+            .map(|(index, _)| Some(index))
+            .ok_or(SoarError::UnknownEvent { chain, hash }),
+        EventSelector::Timestamp(timestamp) => Ok(events
+            .enumerate()
+            .rev()
+            .find(|(_, (_, event_timestamp))| *event_timestamp <= timestamp)
+            .map(|(index, _)| index)),
+    }
+}
+
+/// Bounds of the segment after `from` (exclusive) up to `to` (inclusive).
+fn segment(
+    chain: Chain,
+    from: Option<usize>,
+    to: Option<usize>,
+    to_selector: EventSelector,
+) -> Result<(Option<usize>, usize), SoarError> {
+    let to = match (to, to_selector) {
+        (Some(to), _) => to,
+        (None, EventSelector::Timestamp(timestamp)) => {
+            return Err(SoarError::NoEventAt { chain, timestamp })
+        }
+        (None, EventSelector::Hash(hash)) => return Err(SoarError::UnknownEvent { chain, hash }),
+    };
+    match from {
+        Some(from) if from > to => Err(SoarError::ReversedSegment { chain }),
+        _ => Ok((from, to)),
+    }
+}
+
+impl InputBuilder {
+    pub fn new(
+        stake_events: Vec<StakeChainExtendedEvent>,
+        reward_events: Vec<RewardChainExtendedEvent>,
+    ) -> Self {
+        Self {
+            stake_events,
+            reward_events,
+        }
+    }
+
+    /// Parses the dumps, with amounts and hashes either as strings or as limb and byte arrays.
+    pub fn from_json(stakes: &[u8], rewards: &[u8]) -> Result<Self, SoarError> {
+        Ok(Self::new(
+            serde_json::from_slice(stakes)?,
+            serde_json::from_slice(rewards)?,
+        ))
+    }
+
+    /// Slices the segments selected for `user`, picks the user's stake events at both ends
+    /// of the stake segment and the stake history before it, and checks the result with
+    /// `RewardCalculator::verify`.
+    pub fn build(
+        &self,
+        user: Address,
+        selection: SegmentSelection,
+    ) -> Result<RewardCalculator, SoarError> {
+        let stakes = || {
+            self.stake_events
+                .iter()
+                .map(|event| (event.current_event_hash, event.timestamp))
+        };
+        let rewards = || {
+            self.reward_events
+                .iter()
+                .map(|event| (event.current_event_hash, event.timestamp))
+        };
+        let from_stake = match selection.from_stake {
+            Some(selector) => position(Chain::Stake, selector, stakes())?,
+            None => None,
+        };
+        let (from_stake, to_stake) = segment(
+            Chain::Stake,
+            from_stake,
+            position(Chain::Stake, selection.to_stake, stakes())?,
+            selection.to_stake,
+        )?;
+        let from_reward = match selection.from_reward {
+            Some(selector) => position(Chain::Reward, selector, rewards())?,
+            None => None,
+        };
+        let (from_reward, to_reward) = segment(
+            Chain::Reward,
+            from_reward,
+            position(Chain::Reward, selection.to_reward, rewards())?,
+            selection.to_reward,
+        )?;
+
+        // The user's latest stake event among the first `end` stake events.
+        let user_stake_event = |end: usize| {
+            self.stake_events[..end]
+                .iter()
+                .rev()
+                .find(|event| event.user == user)
+                .cloned()
+        };
+        let stake_start = from_stake.map_or(0, |from| from + 1);
+        let reward_start = from_reward.map_or(0, |from| from + 1);
+
+        // The history reaches back to the latest event of every staker holding stake at
+        // the start of the stake segment.
+        let mut latest_events = BTreeMap::new();
+        for (index, event) in self.stake_events[..stake_start].iter().enumerate() {
+            latest_events.insert(event.user, (index, event.total_user_stake));
+        }
+        let history_start = latest_events
+            .values()
+            .filter(|(_, stake)| !stake.is_zero())
+            .map(|(index, _)| *index)
+            .min()
+            .unwrap_or(stake_start);
+
+        let mut calculator = RewardCalculator {
+            user,
+            stake_events: self.stake_events[stake_start..=to_stake].to_vec(),
+            reward_events: self.reward_events[reward_start..=to_reward].to_vec(),
+            claim: RewardClaimParameters {
+                user,
+                from_reward_event: from_reward.map(|from| self.reward_events[from].clone()),
+                to_reward_event: self.reward_events[to_reward].clone(),
+                from_stake_event: from_stake.map(|from| self.stake_events[from].clone()),
+                to_stake_event: self.stake_events[to_stake].clone(),
+                from_user_stake_event: user_stake_event(stake_start),
+                to_user_stake_event: user_stake_event(to_stake + 1),
+            },
+            stake_history: self.stake_events[history_start..stake_start].to_vec(),
+        };
+        calculator.verify()?;
+        Ok(calculator)
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+
+use crate::error::SoarError;
+use crate::reward::RewardChainExtendedEvent;
+use crate::reward_calculator::RewardCalculator;
+use crate::rounding::RewardDust;
+use crate::stake::StakeChainExtendedEvent;
+use crate::types::{mul_div, Address, U256};
+
+/// A staker's state at the end of the events applied to a `RewardLedger`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LedgerEntry {
+    pub stake: U256,
+    /// Reward token units accrued over the applied events.
+    pub reward: U256,
+}
+
+/// Rewards of every staker over a stake and reward segment in a single pass.
+///
+/// Every reward event is shared out among the stakers of the moment, each staker's share
+/// floored as with `RoundingPolicy::FloorPerEvent`, so every user's reward equals the one
+/// `RewardCalculator::calculate_reward` computes for them. Stakers that held
+/// stake before the segment must be seeded with `with_user_stake`, otherwise they start
+/// from zero. Every reward event must distribute the same token, see
+/// `RewardChainExtendedEvent::reward_token`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RewardLedger {
+    total_stake: U256,
+    /// Token distributed by the reward events applied so far.
+    token: Option<Address>,
+    accounts: BTreeMap<Address, LedgerEntry>,
+    /// Every applied reward event with what the stakers were paid of it.
+    dust: Vec<RewardDust>,
+}
+
+impl RewardLedger {
+    /// A ledger starting from `total_stake` with no known stakers.
+    pub fn new(total_stake: U256) -> Self {
+        Self {
+            total_stake,
+            ..Self::default()
+        }
+    }
+
+    /// Seeds the stake `user` holds at the start of the segment.
+    pub fn with_user_stake(mut self, user: Address, stake: U256) -> Self {
+        self.accounts.insert(
+            user,
+            LedgerEntry {
+                stake,
+                ..LedgerEntry::default()
+            },
+        );
+        self
+    }
+
+    /// Verifies the calculator's chains and applies its segment, seeded with the total stake
+    /// and the calculator user's stake at the start of the segment.
+    pub fn from_calculator(calculator: &mut RewardCalculator) -> Result<Self, SoarError> {
+        calculator.verify_chains()?;
+        let mut ledger = Self::new(calculator.initial_total_stake());
+        if let Some(event) = &calculator.claim.from_user_stake_event {
+            ledger = ledger.with_user_stake(event.user, event.total_user_stake);
+        }
+        ledger.apply(&calculator.stake_events, &calculator.reward_events)?;
+        Ok(ledger)
+    }
+
+    /// Verifies the calculator's chains and applies its segment, seeded with the stake of
+    /// every staker at its start, see `RewardCalculator::initial_staker_stakes`.
+    pub fn from_stake_history(calculator: &mut RewardCalculator) -> Result<Self, SoarError> {
+        calculator.verify_chains()?;
+        let mut ledger = Self::new(calculator.initial_total_stake());
+        for (user, stake) in calculator.initial_staker_stakes()? {
+            ledger = ledger.with_user_stake(user, stake);
+        }
+        ledger.apply(&calculator.stake_events, &calculator.reward_events)?;
+        Ok(ledger)
+    }
+
+    /// Applies the stake and reward events of a segment. As in the calculator, a stake
+    /// event only applies to reward events with a later timestamp. Fails without applying
+    /// anything if the reward events distribute another token than the ones before.
+    pub fn apply(
+        &mut self,
+        stake_events: &[StakeChainExtendedEvent],
+        reward_events: &[RewardChainExtendedEvent],
+    ) -> Result<(), SoarError> {
+        let token = self
+            .token
+            .or_else(|| reward_events.first().map(|event| event.reward_token()));
+        if reward_events
+            .iter()
+            .any(|event| Some(event.reward_token()) != token)
+        {
+            return Err(SoarError::MultipleRewardTokens);
+        }
+        self.token = token;
+        let mut reward_events = reward_events.iter().peekable();
+        for stake_event in stake_events {
+            while let Some(reward_event) =
+                reward_events.next_if(|event| event.timestamp <= stake_event.timestamp)
+            {
+                self.distribute(reward_event)?;
+            }
+            self.accounts.entry(stake_event.user).or_default().stake = stake_event.total_user_stake;
+            self.total_stake = stake_event.total_staked;
+        }
+        for reward_event in reward_events {
+            self.distribute(reward_event)?;
+        }
+        Ok(())
+    }
+
+    /// Credits every staker with their share of `reward_event` and records the dust left.
+    fn distribute(&mut self, reward_event: &RewardChainExtendedEvent) -> Result<(), SoarError> {
+        let mut paid = U256::zero();
+        if !self.total_stake.is_zero() {
+            for entry in self.accounts.values_mut() {
+                if entry.stake.is_zero() {
+                    continue;
+                }
+                let share = mul_div(entry.stake, reward_event.amount, self.total_stake)?;
+                entry.reward = entry.reward.checked_add(share).ok_or(SoarError::Overflow)?;
+                paid = paid.checked_add(share).ok_or(SoarError::Overflow)?;
+            }
+        }
+        // The stakes never add up to more than the total stake, so neither do the shares.
+        let dust = reward_event
+            .amount
+            .checked_sub(paid)
+            .ok_or(SoarError::Overflow)?;
+        self.dust.push(RewardDust {
+            reward_event_hash: reward_event.current_event_hash,
+            amount: reward_event.amount,
+            paid,
+            dust,
+        });
+        Ok(())
+    }
+
+    pub fn total_stake(&self) -> U256 {
+        self.total_stake
+    }
+
+    /// Every applied reward event with what the known stakers were paid of it, in order. This
+    /// is the dust left in the treasury once every staker is known, see
+    /// `from_stake_history`.
+    pub fn dust(&self) -> &[RewardDust] {
+        &self.dust
+    }
+
+    /// The dust of every applied reward event together.
+    pub fn total_dust(&self) -> Result<U256, SoarError> {
+        self.dust.iter().try_fold(U256::zero(), |total, event| {
+            total.checked_add(event.dust).ok_or(SoarError::Overflow)
+        })
+    }
+
+    /// The state of `user`, or `None` if they have neither been seeded nor staked.
+    pub fn entry(&self, user: &Address) -> Option<LedgerEntry> {
+        self.accounts.get(user).copied()
+    }
+
+    /// The state of every known staker, ordered by address.
+    pub fn entries(&self) -> &BTreeMap<Address, LedgerEntry> {
+        &self.accounts
+    }
+}
@@ -1,7 +1,19 @@
 #[macro_use]
 extern crate uint;
 
+pub mod breakdown;
+pub mod dataset;
+pub mod engine;
+pub mod error;
+pub mod input;
+pub mod ledger;
+pub mod logs;
+pub mod merkle;
+pub mod packed;
 pub mod reward;
 pub mod reward_calculator;
+pub mod rounding;
+pub mod simulator;
 pub mod stake;
+pub mod state;
 pub mod types;
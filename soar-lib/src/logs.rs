@@ -0,0 +1,238 @@
+//! Decoding of the raw logs emitted by `StakeChain.sol` and `RewardChain.sol`, as returned by
+//! `eth_getLogs`, into stake and reward events.
+
+use alloy_sol_types::{sol, SolEvent, Word};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::error::SoarError;
+use crate::reward::RewardChainExtendedEvent;
+use crate::stake::StakeChainExtendedEvent;
+use crate::types::{deserialize_bytes, parse_bytes, strip_hex_prefix, Address, Bytes32, U256};
+
+sol! {
+    event StakeChainExtended(
+        address indexed user,
+        bool isStake,
+        uint256 amount,
+        uint256 totalStaked,
+        uint256 totalUserStake,
+        uint256 timestamp,
+        bytes32 previous,
+        bytes32 current
+    );
+
+    event RewardsAdded(
+        uint256 amount,
+        uint256 totalRewards,
+        uint256 timestamp,
+        bytes32 previousRewardChain,
+        bytes32 currentRewardChain
+    );
+
+    event TokenRewardsAdded(
+        address token,
+        uint256 amount,
+        uint256 totalRewards,
+        uint256 timestamp,
+        bytes32 previousRewardChain,
+        bytes32 currentRewardChain
+    );
+}
+
+/// A log entry in the shape returned by `eth_getLogs`. Only the fields needed to decode the
+/// event and locate it on chain are kept.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawLog {
+    /// The contract that emitted the log.
+    #[serde(deserialize_with = "deserialize_bytes")]
+    pub address: Address,
+    #[serde(deserialize_with = "deserialize_topics")]
+    pub topics: Vec<Bytes32>,
+    #[serde(deserialize_with = "deserialize_data")]
+    pub data: Vec<u8>,
+    #[serde(deserialize_with = "deserialize_quantity")]
+    pub block_number: u64,
+    #[serde(deserialize_with = "deserialize_bytes")]
+    pub transaction_hash: Bytes32,
+    #[serde(deserialize_with = "deserialize_quantity")]
+    pub log_index: u64,
+}
+
+/// An event decoded from a log, along with where the log was emitted.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoggedEvent<T> {
+    #[serde(flatten)]
+    pub event: T,
+    pub block_number: u64,
+    #[serde(deserialize_with = "deserialize_bytes")]
+    pub transaction_hash: Bytes32,
+    pub log_index: u64,
+}
+
+fn deserialize_topics<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Bytes32>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|topic| parse_bytes("topics", topic).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+fn deserialize_data<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let data = String::deserialize(deserializer)?;
+    hex::decode(strip_hex_prefix(&data)).map_err(|_| {
+        serde::de::Error::custom(SoarError::Parse {
+            field: "data",
+            value: data.clone(),
+        })
+    })
+}
+
+/// Deserializes a JSON-RPC quantity, a `0x`-prefixed hex string, or a plain number.
+fn deserialize_quantity<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Quantity {
+        Number(u64),
+        Hex(String),
+    }
+    match Quantity::deserialize(deserializer)? {
+        Quantity::Number(value) => Ok(value),
+        Quantity::Hex(value) => u64::from_str_radix(strip_hex_prefix(&value), 16).map_err(|_| {
+            serde::de::Error::custom(SoarError::Parse {
+                field: "quantity",
+                value,
+            })
+        }),
+    }
+}
+
+fn u256(value: alloy_primitives::U256) -> U256 {
+    U256::from_big_endian(&value.to_be_bytes::<32>())
+}
+
+impl RawLog {
+    fn decode<E: SolEvent>(&self) -> Result<E, SoarError> {
+        E::decode_raw_log(
+            self.topics.iter().map(|topic| Word::from(*topic)),
+            &self.data,
+            true,
+        )
+        .map_err(|error| SoarError::InvalidLog {
+            log_index: self.log_index,
+            message: error.to_string(),
+        })
+    }
+
+    fn logged<T>(&self, event: T) -> LoggedEvent<T> {
+        LoggedEvent {
+            event,
+            block_number: self.block_number,
+            transaction_hash: self.transaction_hash,
+            log_index: self.log_index,
+        }
+    }
+
+    fn unknown_event(&self) -> SoarError {
+        SoarError::InvalidLog {
+            log_index: self.log_index,
+            message: match self.topics.first() {
+                Some(topic) => format!("unknown event signature 0x{}", hex::encode(topic)),
+                None => "anonymous log".to_string(),
+            },
+        }
+    }
+
+    /// Decodes a `StakeChainExtended` log.
+    pub fn stake_event(&self) -> Result<LoggedEvent<StakeChainExtendedEvent>, SoarError> {
+        if self.topics.first() != Some(&StakeChainExtended::SIGNATURE_HASH.0) {
+            return Err(self.unknown_event());
+        }
+        let log: StakeChainExtended = self.decode()?;
+        Ok(self.logged(StakeChainExtendedEvent {
+            user: log.user.0 .0,
+            is_stake: log.isStake,
+            amount: u256(log.amount),
+            total_staked: u256(log.totalStaked),
+            total_user_stake: u256(log.totalUserStake),
+            timestamp: u256(log.timestamp),
+            previous_event_hash: log.previous.0,
+            current_event_hash: log.current.0,
+            hash: None,
+        }))
+    }
+
+    /// Decodes a `RewardsAdded` or a `TokenRewardsAdded` log.
+    pub fn reward_event(&self) -> Result<LoggedEvent<RewardChainExtendedEvent>, SoarError> {
+        let event = match self.topics.first() {
+            Some(topic) if *topic == RewardsAdded::SIGNATURE_HASH.0 => {
+                let log: RewardsAdded = self.decode()?;
+                RewardChainExtendedEvent {
+                    token: None,
+                    amount: u256(log.amount),
+                    total_reward: u256(log.totalRewards),
+                    timestamp: u256(log.timestamp),
+                    previous_event_hash: log.previousRewardChain.0,
+                    current_event_hash: log.currentRewardChain.0,
+                    hash: None,
+                }
+            }
+            Some(topic) if *topic == TokenRewardsAdded::SIGNATURE_HASH.0 => {
+                let log: TokenRewardsAdded = self.decode()?;
+                RewardChainExtendedEvent {
+                    token: Some(log.token.0 .0),
+                    amount: u256(log.amount),
+                    total_reward: u256(log.totalRewards),
+                    timestamp: u256(log.timestamp),
+                    previous_event_hash: log.previousRewardChain.0,
+                    current_event_hash: log.currentRewardChain.0,
+                    hash: None,
+                }
+            }
+            _ => return Err(self.unknown_event()),
+        };
+        Ok(self.logged(event))
+    }
+}
+
+/// Decodes the `StakeChainExtended` logs emitted by the `StakeChain` contract at
+/// `stake_chain` among `logs`, in chain order. Logs of other events or contracts are skipped.
+pub fn stake_events(
+    logs: &[RawLog],
+    stake_chain: &Address,
+) -> Result<Vec<LoggedEvent<StakeChainExtendedEvent>>, SoarError> {
+    let mut events: Vec<_> = logs
+        .iter()
+        .filter(|log| {
+            log.address == *stake_chain
+                && log.topics.first() == Some(&StakeChainExtended::SIGNATURE_HASH.0)
+        })
+        .map(RawLog::stake_event)
+        .collect::<Result<_, _>>()?;
+    events.sort_by_key(|event| (event.block_number, event.log_index));
+    Ok(events)
+}
+
+/// Decodes the `RewardsAdded` and `TokenRewardsAdded` logs emitted by the `RewardChain`
+/// contract at `reward_chain` among `logs`, in chain order. Logs of other events or
+/// contracts are skipped.
+pub fn reward_events(
+    logs: &[RawLog],
+    reward_chain: &Address,
+) -> Result<Vec<LoggedEvent<RewardChainExtendedEvent>>, SoarError> {
+    let mut events: Vec<_> = logs
+        .iter()
+        .filter(|log| {
+            log.address == *reward_chain
+                && log.topics.first().is_some_and(|topic| {
+                    *topic == RewardsAdded::SIGNATURE_HASH.0
+                        || *topic == TokenRewardsAdded::SIGNATURE_HASH.0
+                })
+        })
+        .map(RawLog::reward_event)
+        .collect::<Result<_, _>>()?;
+    events.sort_by_key(|event| (event.block_number, event.log_index));
+    Ok(events)
+}
@@ -0,0 +1,157 @@
+use crate::error::SoarError;
+use crate::ledger::RewardLedger;
+use crate::reward_calculator::RewardCalculator;
+use crate::types::{keccak256, Address, Bytes32, Zero, U256};
+
+/// Length of a leaf preimage, `abi.encodePacked(address user, uint256 reward)`.
+pub const LEAF_PACKED_LEN: usize = 20 + 32;
+
+/// Hash of a `(user, cumulative reward)` leaf, packing the same `user` and `total_rewards`
+/// as `PublicValuesStruct`.
+pub fn leaf_hash(user: &Address, reward: U256) -> Bytes32 {
+    let mut packed = [0u8; LEAF_PACKED_LEN];
+    packed[..20].copy_from_slice(user);
+    packed[20..].copy_from_slice(&reward.to_big_endian());
+    keccak256(&packed)
+}
+
+/// Hash of two sibling nodes. The pair is sorted first, as in OpenZeppelin's `MerkleProof`,
+/// so proofs do not need to record on which side each sibling sits.
+fn node_hash(a: &Bytes32, b: &Bytes32) -> Bytes32 {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    let mut packed = [0u8; 64];
+    packed[..32].copy_from_slice(left);
+    packed[32..].copy_from_slice(right);
+    keccak256(&packed)
+}
+
+/// Checks a proof produced by `MerkleTree::proof`, matching `MerkleProof.verify`.
+pub fn verify_proof(leaf: Bytes32, proof: &[Bytes32], root: Bytes32) -> bool {
+    proof
+        .iter()
+        .fold(leaf, |node, sibling| node_hash(&node, sibling))
+        == root
+}
+
+/// Binary Merkle tree over leaf hashes. A node without a sibling is carried up unchanged.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleTree {
+    /// Every level of the tree, starting with the leaves and ending with the root.
+    layers: Vec<Vec<Bytes32>>,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: Vec<Bytes32>) -> Self {
+        let mut layers = vec![leaves];
+        while layers[layers.len() - 1].len() > 1 {
+            let layer = layers[layers.len() - 1]
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    [node] => *node,
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(layer);
+        }
+        Self { layers }
+    }
+
+    /// The root of the tree, or the zero hash for a tree without leaves.
+    pub fn root(&self) -> Bytes32 {
+        match self.layers[self.layers.len() - 1].first() {
+            Some(root) => *root,
+            None => Bytes32::zero(),
+        }
+    }
+
+    /// Sibling hashes from the leaf at `index` up to the root.
+    pub fn proof(&self, mut index: usize) -> Option<Vec<Bytes32>> {
+        if index >= self.layers[0].len() {
+            return None;
+        }
+        let mut proof = Vec::new();
+        for layer in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        Some(proof)
+    }
+}
+
+/// A user's leaf in a `RewardTree` along with its proof.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RewardProof {
+    pub user: Address,
+    pub reward: U256,
+    pub proof: Vec<Bytes32>,
+}
+
+impl RewardProof {
+    pub fn verify(&self, root: Bytes32) -> bool {
+        verify_proof(leaf_hash(&self.user, self.reward), &self.proof, root)
+    }
+}
+
+/// Merkle tree over the rewards of every staker of a segment, ordered by address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RewardTree {
+    rewards: Vec<(Address, U256)>,
+    tree: MerkleTree,
+}
+
+impl RewardTree {
+    pub fn from_ledger(ledger: &RewardLedger) -> Result<Self, SoarError> {
+        let rewards: Vec<_> = ledger
+            .entries()
+            .iter()
+            .map(|(user, entry)| (*user, entry.reward))
+            .collect();
+        let tree = MerkleTree::new(
+            rewards
+                .iter()
+                .map(|(user, reward)| leaf_hash(user, *reward))
+                .collect(),
+        );
+        Ok(Self { rewards, tree })
+    }
+
+    /// Verifies the calculator's chains and stake history and builds the tree of the
+    /// rewards over its segment, see `RewardLedger::from_stake_history`. Each leaf holds the
+    /// reward `RewardCalculator::calculate_reward` gives the staker's claim.
+    pub fn from_calculator(calculator: &mut RewardCalculator) -> Result<Self, SoarError> {
+        Self::from_ledger(&RewardLedger::from_stake_history(calculator)?)
+    }
+
+    pub fn root(&self) -> Bytes32 {
+        self.tree.root()
+    }
+
+    /// The `(user, reward)` leaves of the tree in order.
+    pub fn rewards(&self) -> &[(Address, U256)] {
+        &self.rewards
+    }
+
+    /// Sum of the rewards of every leaf.
+    pub fn total_reward(&self) -> Result<U256, SoarError> {
+        self.rewards
+            .iter()
+            .try_fold(U256::zero(), |total, (_, reward)| {
+                total.checked_add(*reward).ok_or(SoarError::Overflow)
+            })
+    }
+
+    pub fn proof(&self, user: &Address) -> Option<RewardProof> {
+        let index = self
+            .rewards
+            .binary_search_by(|(leaf_user, _)| leaf_user.cmp(user))
+            .ok()?;
+        Some(RewardProof {
+            user: *user,
+            reward: self.rewards[index].1,
+            proof: self.tree.proof(index)?,
+        })
+    }
+}
@@ -0,0 +1,217 @@
+//! Compact binary encoding of `RewardCalculator` inputs for the zkVM guest, which spends a
+//! large share of its cycles parsing JSON otherwise.
+//!
+//! All integers are big-endian. After the header `PACKED_INPUT_MAGIC || PACKED_INPUT_VERSION`
+//! the input consists of:
+//!
+//! - `user` (20 bytes) and `claim.user` (20 bytes),
+//! - the claim events in the order `fromRewardChainEvent`, `toRewardChainEvent`,
+//!   `fromStakeChainEvent`, `toStakeChainEvent`, `fromUserStakeChainEvent`,
+//!   `toUserStakeChainEvent`, the optional ones preceded by a presence byte,
+//! - the stake events, the reward events and the stake history, each list preceded by its
+//!   length as a `u32`.
+//!
+//! A stake event is its `abi.encodePacked` form of `STAKE_EVENT_PACKED_LEN` bytes. A reward
+//! event is `PACKED_REWARD_EVENT_LEN` bytes: a byte set to 1 for token events, the token
+//! (zero for the reward chain's own token) and the `abi.encodePacked` form without the token.
+//! Current event hashes are not encoded since they are the hashes of the packed events, so
+//! events are hashed in place without being encoded again.
+
+use crate::error::SoarError;
+use crate::reward::{RewardChainExtendedEvent, TOKEN_REWARD_EVENT_PACKED_LEN};
+use crate::reward_calculator::{RewardCalculator, RewardClaimParameters};
+use crate::stake::{StakeChainExtendedEvent, STAKE_EVENT_PACKED_LEN};
+use crate::types::Address;
+
+/// First bytes of every packed input. JSON inputs never start with them.
+pub const PACKED_INPUT_MAGIC: [u8; 4] = *b"SOAR";
+
+/// Version of the packed input layout.
+pub const PACKED_INPUT_VERSION: u8 = 2;
+
+/// Length of a reward event in a packed input.
+pub const PACKED_REWARD_EVENT_LEN: usize = 1 + TOKEN_REWARD_EVENT_PACKED_LEN;
+
+/// Whether `input` is a packed input rather than JSON.
+pub fn is_packed_input(input: &[u8]) -> bool {
+    input.starts_with(&PACKED_INPUT_MAGIC)
+}
+
+fn encode_stake_event(packed: &mut Vec<u8>, event: &StakeChainExtendedEvent) {
+    packed.extend_from_slice(&event.encode_packed());
+}
+
+fn encode_reward_event(packed: &mut Vec<u8>, event: &RewardChainExtendedEvent) {
+    packed.push(event.token.is_some() as u8);
+    if event.token.is_none() {
+        packed.extend_from_slice(&Address::default());
+    }
+    packed.extend_from_slice(&event.encode_packed());
+}
+
+fn encode_optional<T>(packed: &mut Vec<u8>, event: &Option<T>, encode: fn(&mut Vec<u8>, &T)) {
+    match event {
+        Some(event) => {
+            packed.push(1);
+            encode(packed, event);
+        }
+        None => packed.push(0),
+    }
+}
+
+fn encode_list<T>(packed: &mut Vec<u8>, events: &[T], encode: fn(&mut Vec<u8>, &T)) {
+    packed.extend_from_slice(&(events.len() as u32).to_be_bytes());
+    for event in events {
+        encode(packed, event);
+    }
+}
+
+/// Encodes a calculator input in the packed layout described in the module documentation.
+pub fn encode_input(calculator: &RewardCalculator) -> Vec<u8> {
+    let claim = &calculator.claim;
+    let mut packed = Vec::with_capacity(
+        PACKED_INPUT_MAGIC.len()
+            + 1
+            + 40
+            + 4 * PACKED_REWARD_EVENT_LEN
+            + 4 * STAKE_EVENT_PACKED_LEN
+            + 12
+            + (calculator.stake_events.len() + calculator.stake_history.len())
+                * STAKE_EVENT_PACKED_LEN
+            + calculator.reward_events.len() * PACKED_REWARD_EVENT_LEN,
+    );
+    packed.extend_from_slice(&PACKED_INPUT_MAGIC);
+    packed.push(PACKED_INPUT_VERSION);
+    packed.extend_from_slice(&calculator.user);
+    packed.extend_from_slice(&claim.user);
+    encode_optional(&mut packed, &claim.from_reward_event, encode_reward_event);
+    encode_reward_event(&mut packed, &claim.to_reward_event);
+    encode_optional(&mut packed, &claim.from_stake_event, encode_stake_event);
+    encode_stake_event(&mut packed, &claim.to_stake_event);
+    encode_optional(
+        &mut packed,
+        &claim.from_user_stake_event,
+        encode_stake_event,
+    );
+    encode_optional(&mut packed, &claim.to_user_stake_event, encode_stake_event);
+    encode_list(&mut packed, &calculator.stake_events, encode_stake_event);
+    encode_list(&mut packed, &calculator.reward_events, encode_reward_event);
+    encode_list(&mut packed, &calculator.stake_history, encode_stake_event);
+    packed
+}
+
+/// Reads a packed input front to back.
+struct Reader<'a> {
+    input: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn error(&self) -> SoarError {
+        SoarError::PackedInput {
+            offset: self.offset,
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SoarError> {
+        let bytes = self
+            .input
+            .get(self.offset..self.offset + len)
+            .ok_or_else(|| self.error())?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn flag(&mut self) -> Result<bool, SoarError> {
+        match self.take(1)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => {
+                self.offset -= 1;
+                Err(self.error())
+            }
+        }
+    }
+
+    fn address(&mut self) -> Result<Address, SoarError> {
+        Ok(self.take(20)?.try_into().unwrap())
+    }
+
+    fn stake_event(&mut self) -> Result<StakeChainExtendedEvent, SoarError> {
+        let offset = self.offset;
+        StakeChainExtendedEvent::decode_packed(self.take(STAKE_EVENT_PACKED_LEN)?)
+            .ok_or(SoarError::PackedInput { offset })
+    }
+
+    fn reward_event(&mut self) -> Result<RewardChainExtendedEvent, SoarError> {
+        let offset = self.offset;
+        let has_token = self.flag()?;
+        let packed = self.take(TOKEN_REWARD_EVENT_PACKED_LEN)?;
+        // Events of the reward chain's own token are hashed without the zero token.
+        let packed = if has_token {
+            packed
+        } else if packed[..20] == Address::default() {
+            &packed[20..]
+        } else {
+            return Err(SoarError::PackedInput { offset });
+        };
+        RewardChainExtendedEvent::decode_packed(packed).ok_or(SoarError::PackedInput { offset })
+    }
+
+    fn optional<T>(
+        &mut self,
+        decode: fn(&mut Self) -> Result<T, SoarError>,
+    ) -> Result<Option<T>, SoarError> {
+        match self.flag()? {
+            true => decode(self).map(Some),
+            false => Ok(None),
+        }
+    }
+
+    fn list<T>(
+        &mut self,
+        decode: fn(&mut Self) -> Result<T, SoarError>,
+    ) -> Result<Vec<T>, SoarError> {
+        let len = u32::from_be_bytes(self.take(4)?.try_into().unwrap()) as usize;
+        // Do not trust the length for the allocation beyond what the input can hold.
+        let mut events = Vec::with_capacity(len.min(self.input.len() / STAKE_EVENT_PACKED_LEN));
+        for _ in 0..len {
+            events.push(decode(self)?);
+        }
+        Ok(events)
+    }
+}
+
+/// Decodes an input encoded by `encode_input`.
+pub fn decode_input(input: &[u8]) -> Result<RewardCalculator, SoarError> {
+    let mut reader = Reader { input, offset: 0 };
+    if reader.take(PACKED_INPUT_MAGIC.len())? != PACKED_INPUT_MAGIC {
+        return Err(SoarError::PackedInput { offset: 0 });
+    }
+    if reader.take(1)?[0] != PACKED_INPUT_VERSION {
+        return Err(SoarError::PackedInput {
+            offset: PACKED_INPUT_MAGIC.len(),
+        });
+    }
+    let user = reader.address()?;
+    let claim = RewardClaimParameters {
+        user: reader.address()?,
+        from_reward_event: reader.optional(Reader::reward_event)?,
+        to_reward_event: reader.reward_event()?,
+        from_stake_event: reader.optional(Reader::stake_event)?,
+        to_stake_event: reader.stake_event()?,
+        from_user_stake_event: reader.optional(Reader::stake_event)?,
+        to_user_stake_event: reader.optional(Reader::stake_event)?,
+    };
+    let calculator = RewardCalculator {
+        user,
+        stake_events: reader.list(Reader::stake_event)?,
+        reward_events: reader.list(Reader::reward_event)?,
+        claim,
+        stake_history: reader.list(Reader::stake_event)?,
+    };
+    if reader.offset != input.len() {
+        return Err(reader.error());
+    }
+    Ok(calculator)
+}
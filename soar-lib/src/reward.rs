@@ -1,8 +1,21 @@
-use crate::types::{Bytes32, U256};
-use serde::Deserialize;
+use crate::error::SoarError;
+use crate::types::{
+    deserialize_bytes, deserialize_optional_bytes, hex_string, keccak256, parse_bytes, parse_u256,
+    Address, Bytes32, U256,
+};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Deserialize)]
+/// Length of the `abi.encodePacked` payload hashed by `RewardChain.addRewards`.
+pub const REWARD_EVENT_PACKED_LEN: usize = 32 * 3 + 32;
+
+/// Length of the payload hashed by `RewardChain.addTokenRewards`, which prefixes the token.
+pub const TOKEN_REWARD_EVENT_PACKED_LEN: usize = 20 + REWARD_EVENT_PACKED_LEN;
+
+/// An event with its amounts as decimal strings and its token and hashes as hex strings.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RewardChainExtendedEventJSON {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
     pub amount: String,
     #[serde(rename = "totalRewards")]
     pub total_reward: String,
@@ -13,58 +26,128 @@ pub struct RewardChainExtendedEventJSON {
     pub current_event_hash: String,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RewardChainExtendedEvent {
+    /// Token distributed by the event, `None` for the reward chain's own reward token.
+    /// `total_reward` is the running total of this token. The zero address is not a valid
+    /// token, as `RewardChain` keeps the totals of its own reward token apart.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_optional_bytes"
+    )]
+    pub token: Option<Address>,
     pub amount: U256,
     #[serde(rename = "totalRewards")]
     pub total_reward: U256,
     pub timestamp: U256,
-    #[serde(rename = "previousRewardChain")]
+    #[serde(rename = "previousRewardChain", deserialize_with = "deserialize_bytes")]
     pub previous_event_hash: Bytes32,
-    #[serde(rename = "currentRewardChain")]
+    #[serde(rename = "currentRewardChain", deserialize_with = "deserialize_bytes")]
     pub current_event_hash: Bytes32,
 
-    hash: Option<Bytes32>,
+    /// Cache of `hash()`, not part of the serialized event.
+    #[serde(skip)]
+    pub(crate) hash: Option<Bytes32>,
 }
 
-impl From<RewardChainExtendedEventJSON> for RewardChainExtendedEvent {
-    fn from(event: RewardChainExtendedEventJSON) -> Self {
-        Self {
-            amount: U256::from_dec_str(&event.amount).unwrap(),
-            total_reward: U256::from_dec_str(&event.total_reward).unwrap(),
-            timestamp: U256::from_dec_str(&event.timestamp).unwrap(),
-            previous_event_hash: hex::decode(event.previous_event_hash)
-                .unwrap()
-                .try_into()
-                .unwrap(),
-            current_event_hash: hex::decode(event.current_event_hash)
-                .unwrap()
-                .try_into()
-                .unwrap(),
+impl TryFrom<RewardChainExtendedEventJSON> for RewardChainExtendedEvent {
+    type Error = SoarError;
+
+    fn try_from(event: RewardChainExtendedEventJSON) -> Result<Self, Self::Error> {
+        Ok(Self {
+            token: event
+                .token
+                .map(|token| {
+                    let address: Address = parse_bytes("token", &token)?;
+                    if address == Address::default() {
+                        return Err(SoarError::Parse {
+                            field: "token",
+                            value: token,
+                        });
+                    }
+                    Ok(address)
+                })
+                .transpose()?,
+            amount: parse_u256("amount", &event.amount)?,
+            total_reward: parse_u256("totalRewards", &event.total_reward)?,
+            timestamp: parse_u256("timestamp", &event.timestamp)?,
+            previous_event_hash: parse_bytes("previousRewardChain", &event.previous_event_hash)?,
+            current_event_hash: parse_bytes("currentRewardChain", &event.current_event_hash)?,
             hash: None,
+        })
+    }
+}
+
+impl From<&RewardChainExtendedEvent> for RewardChainExtendedEventJSON {
+    fn from(event: &RewardChainExtendedEvent) -> Self {
+        Self {
+            token: event.token.as_ref().map(|token| hex_string(token)),
+            amount: event.amount.to_string(),
+            total_reward: event.total_reward.to_string(),
+            timestamp: event.timestamp.to_string(),
+            previous_event_hash: hex_string(&event.previous_event_hash),
+            current_event_hash: hex_string(&event.current_event_hash),
         }
     }
 }
 
 impl RewardChainExtendedEvent {
+    /// Packs the event the same way `RewardChain.addRewards` does before hashing:
+    /// `abi.encodePacked(amount, totalRewards, timestamp, previousRewardChain)`, prefixed
+    /// with the token for events of `RewardChain.addTokenRewards`.
+    pub fn encode_packed(&self) -> Vec<u8> {
+        let mut packed = Vec::with_capacity(TOKEN_REWARD_EVENT_PACKED_LEN);
+        if let Some(token) = &self.token {
+            packed.extend_from_slice(token);
+        }
+        packed.extend_from_slice(&self.amount.to_big_endian());
+        packed.extend_from_slice(&self.total_reward.to_big_endian());
+        packed.extend_from_slice(&self.timestamp.to_big_endian());
+        packed.extend_from_slice(&self.previous_event_hash);
+        packed
+    }
+
+    /// Decodes an event from its `encode_packed` form, with the token prefix if `packed` is
+    /// `TOKEN_REWARD_EVENT_PACKED_LEN` bytes long and the token is not the zero address. The
+    /// current hash is the hash of `packed`, which is cached right away.
+    pub fn decode_packed(packed: &[u8]) -> Option<Self> {
+        let (token, fields) = match packed.len() {
+            REWARD_EVENT_PACKED_LEN => (None, packed),
+            TOKEN_REWARD_EVENT_PACKED_LEN if packed[..20] != Address::default() => {
+                (Some(packed[..20].try_into().unwrap()), &packed[20..])
+            }
+            _ => return None,
+        };
+        let hash = keccak256(packed);
+        Some(Self {
+            token,
+            amount: U256::from_big_endian(&fields[..32]),
+            total_reward: U256::from_big_endian(&fields[32..64]),
+            timestamp: U256::from_big_endian(&fields[64..96]),
+            previous_event_hash: fields[96..].try_into().unwrap(),
+            current_event_hash: hash,
+            hash: Some(hash),
+        })
+    }
+
+    /// Hash of the event as computed on chain. The result is cached, so the event
+    /// fields must not be modified after the first call.
     pub fn hash(&mut self) -> Bytes32 {
-        // TODO: Construct a hash from the event data
         if self.hash.is_none() {
-            /*
-            let mut hasher = sha3::Keccak256::new();
-            hasher.update(&self.amount.to_bytes_be());
-            hasher.update(&self.total_reward.to_bytes_be());
-            hasher.update(&self.timestamp.to_bytes_be());
-            hasher.update(&self.previous_event_hash);
-            */
-            let result = self.current_event_hash;
-            self.hash = Some(result);
+            self.hash = Some(keccak256(&self.encode_packed()));
         }
         self.hash.unwrap()
     }
 
-    pub fn verify_hash(&mut self, expected: &Bytes32) -> bool {
-        // TODO: check if the constructed hash matches the expected
-        self.hash() == *expected
+    /// The distributed token, with the reward chain's own reward token as the zero address.
+    pub fn reward_token(&self) -> Address {
+        self.token.unwrap_or_default()
+    }
+
+    /// Checks that the supplied `current_event_hash` matches the recomputed hash, rejecting
+    /// events of the zero address token, which `RewardChain` never hashes.
+    pub fn verify_hash(&mut self) -> bool {
+        self.token != Some(Address::default()) && self.hash() == self.current_event_hash
     }
 }
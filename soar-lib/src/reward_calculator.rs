@@ -1,12 +1,20 @@
-use alloy_primitives::Bytes;
-use serde::Deserialize;
+use std::collections::BTreeMap;
 
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{ReplayEngine, RewardEngine};
+use crate::error::{Chain, Endpoint, SoarError};
+use crate::packed::{decode_input, is_packed_input};
 use crate::reward::RewardChainExtendedEvent;
+use crate::rounding::{RewardDistribution, RoundingPolicy};
 use crate::stake::StakeChainExtendedEvent;
-use crate::types::{Address, Bytes32, Zero, U256};
+use crate::types::{
+    deserialize_bytes, keccak256, Address, Bytes32, PublicValuesStruct, TokenAmount, Zero, U256,
+};
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RewardClaimParameters {
+    #[serde(deserialize_with = "deserialize_bytes")]
     pub user: Address,
     #[serde(rename = "fromRewardChainEvent")]
     pub from_reward_event: Option<RewardChainExtendedEvent>,
@@ -22,84 +30,437 @@ pub struct RewardClaimParameters {
     pub to_user_stake_event: Option<StakeChainExtendedEvent>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+/// Hashes of the endpoints of a claim, with the zero hash for missing `from` endpoints and a
+/// missing `to_user_stake_event`, as committed in the public values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClaimHashes {
+    pub from_reward_event: Bytes32,
+    pub to_reward_event: Bytes32,
+    pub from_stake_event: Bytes32,
+    pub to_stake_event: Bytes32,
+    pub from_user_stake_event: Bytes32,
+    pub to_user_stake_event: Bytes32,
+}
+
+impl RewardClaimParameters {
+    /// Hashes of every endpoint of the claim.
+    pub fn hashes(&mut self) -> ClaimHashes {
+        ClaimHashes {
+            from_reward_event: self
+                .from_reward_event
+                .as_mut()
+                .map_or(Bytes32::zero(), |event| event.hash()),
+            to_reward_event: self.to_reward_event.hash(),
+            from_stake_event: self
+                .from_stake_event
+                .as_mut()
+                .map_or(Bytes32::zero(), |event| event.hash()),
+            to_stake_event: self.to_stake_event.hash(),
+            from_user_stake_event: self
+                .from_user_stake_event
+                .as_mut()
+                .map_or(Bytes32::zero(), |event| event.hash()),
+            to_user_stake_event: self
+                .to_user_stake_event
+                .as_mut()
+                .map_or(Bytes32::zero(), |event| event.hash()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RewardCalculator {
+    #[serde(deserialize_with = "deserialize_bytes")]
     pub user: Address,
     //pub stake_snapshot_timestamp: U256,
     //pub reward_snapshot_timestamp: U256,
     pub stake_events: Vec<StakeChainExtendedEvent>,
     pub reward_events: Vec<RewardChainExtendedEvent>,
     pub claim: RewardClaimParameters,
+    /// The stake events up to `claim.from_stake_event` (inclusive), starting at the
+    /// earliest of the latest events of the stakers holding stake at the start of the stake
+    /// segment. Only needed to compute the rewards of all stakers, see
+    /// `initial_staker_stakes`.
+    #[serde(default, rename = "stakeHistory")]
+    pub stake_history: Vec<StakeChainExtendedEvent>,
 }
 
-impl From<Vec<u8>> for RewardCalculator {
-    fn from(input: Vec<u8>) -> Self {
-        serde_json::from_slice(&input).unwrap()
+impl TryFrom<Vec<u8>> for RewardCalculator {
+    type Error = SoarError;
+
+    /// Decodes a packed input, see `packed`, or JSON otherwise.
+    fn try_from(input: Vec<u8>) -> Result<Self, Self::Error> {
+        if is_packed_input(&input) {
+            return decode_input(&input);
+        }
+        Ok(serde_json::from_slice(&input)?)
     }
 }
 
 impl RewardCalculator {
-    pub fn calculate_reward(&mut self) -> U256 {
-        // Calculate the total reward for the user based on reward events and stake events.
-        let mut total_user_stake = U256::zero();
-        if self.claim.from_user_stake_event.is_some() {
-            total_user_stake = self
-                .claim
-                .from_user_stake_event
-                .as_ref()
-                .unwrap()
-                .total_user_stake;
-        }
-        let mut total_stake = U256::zero();
-        let mut current_timestamp = U256::zero();
-        if self.claim.from_stake_event.is_some() {
-            total_stake = self.claim.from_stake_event.as_ref().unwrap().total_staked;
-        }
-        let mut stake_event_index = 0; // Stake index
-        let mut reward_event_index = 0; // Reward Index
-        let mut total_user_reward = U256::zero();
-        let precision = U256::from("1000000000000000000");
-        let zero = U256::zero();
-        while reward_event_index < self.reward_events.len() {
-            current_timestamp = self.reward_events[reward_event_index].timestamp;
-            while stake_event_index < self.stake_events.len()
-                && current_timestamp > self.stake_events[stake_event_index].timestamp
-            {
-                if self.stake_events[stake_event_index].user == self.user {
-                    total_user_stake = self.stake_events[stake_event_index].total_user_stake;
-                }
-                total_stake = self.stake_events[stake_event_index].total_staked;
-                stake_event_index += 1;
-                // TODO: Verify stake event hashes
-                if (stake_event_index <= self.stake_events.len() && stake_event_index > 1) {
-                    assert_eq!(
-                        self.stake_events[stake_event_index - 2].current_event_hash,
-                        self.stake_events[stake_event_index - 1].previous_event_hash
-                    );
-                }
-                if (stake_event_index == 1) {
-                    let mut first_stake_event_hash = Bytes32::zero();
-                    if self.claim.from_stake_event.is_some() {
-                        let mut e = self.claim.from_stake_event.clone().unwrap();
-                        first_stake_event_hash = e.hash();
-                    }
-                    assert_eq!(
-                        first_stake_event_hash,
-                        self.stake_events[0].previous_event_hash
-                    );
+    /// Checks that `reward_events` is exactly the contiguous segment of the reward chain
+    /// after `claim.from_reward_event` (exclusive) up to `claim.to_reward_event` (inclusive).
+    /// A missing `from_reward_event` means the segment starts at the beginning of the chain.
+    fn verify_reward_chain(&mut self) -> Result<(), SoarError> {
+        let (mut previous_hash, mut previous_timestamp) =
+            match self.claim.from_reward_event.as_mut() {
+                Some(event) => (event.hash(), event.timestamp),
+                None => (Bytes32::zero(), U256::zero()),
+            };
+        for (index, event) in self.reward_events.iter_mut().enumerate() {
+            if event.previous_event_hash != previous_hash {
+                return Err(SoarError::ChainLink {
+                    chain: Chain::Reward,
+                    index,
+                    expected: previous_hash,
+                    actual: event.previous_event_hash,
+                });
+            }
+            if !event.verify_hash() {
+                return Err(SoarError::HashMismatch {
+                    chain: Chain::Reward,
+                    index,
+                    supplied: event.current_event_hash,
+                    computed: event.hash(),
+                });
+            }
+            if event.timestamp < previous_timestamp {
+                return Err(SoarError::NonMonotonicTimestamp {
+                    chain: Chain::Reward,
+                    index,
+                    previous: previous_timestamp,
+                    timestamp: event.timestamp,
+                });
+            }
+            previous_hash = event.hash();
+            previous_timestamp = event.timestamp;
+        }
+        let to_hash = self.claim.to_reward_event.hash();
+        if previous_hash != to_hash {
+            return Err(SoarError::EndpointMismatch {
+                chain: Chain::Reward,
+                endpoint: Endpoint::To,
+                expected: to_hash,
+                actual: previous_hash,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks that `stake_events` is exactly the contiguous segment of the stake chain
+    /// after `claim.from_stake_event` (exclusive) up to `claim.to_stake_event` (inclusive),
+    /// including events after the last reward event.
+    fn verify_stake_chain(&mut self) -> Result<(), SoarError> {
+        let (mut previous_hash, mut previous_timestamp) = match self.claim.from_stake_event.as_mut()
+        {
+            Some(event) => (event.hash(), event.timestamp),
+            None => (Bytes32::zero(), U256::zero()),
+        };
+        for (index, event) in self.stake_events.iter_mut().enumerate() {
+            if event.previous_event_hash != previous_hash {
+                return Err(SoarError::ChainLink {
+                    chain: Chain::Stake,
+                    index,
+                    expected: previous_hash,
+                    actual: event.previous_event_hash,
+                });
+            }
+            if !event.verify_hash() {
+                return Err(SoarError::HashMismatch {
+                    chain: Chain::Stake,
+                    index,
+                    supplied: event.current_event_hash,
+                    computed: event.hash(),
+                });
+            }
+            if event.timestamp < previous_timestamp {
+                return Err(SoarError::NonMonotonicTimestamp {
+                    chain: Chain::Stake,
+                    index,
+                    previous: previous_timestamp,
+                    timestamp: event.timestamp,
+                });
+            }
+            previous_hash = event.hash();
+            previous_timestamp = event.timestamp;
+        }
+        let to_hash = self.claim.to_stake_event.hash();
+        if previous_hash != to_hash {
+            return Err(SoarError::EndpointMismatch {
+                chain: Chain::Stake,
+                endpoint: Endpoint::To,
+                expected: to_hash,
+                actual: previous_hash,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks the user's own events in `stake_events` against the committed user snapshots.
+    /// `claim.from_user_stake_event` is the user's latest event at the start of the segment
+    /// (bound on chain by the relayed user snapshot), each of the user's events in the
+    /// segment must extend the user's stake from it, and the last of them, or the starting
+    /// snapshot when the user has no events in the window, must be `claim.to_user_stake_event`.
+    fn verify_user_stake_chain(&mut self) -> Result<(), SoarError> {
+        let (mut user_stake, mut user_stake_hash) = self.initial_user_snapshot()?;
+        for (index, event) in self.stake_events.iter_mut().enumerate() {
+            if event.user != self.user {
+                continue;
+            }
+            let expected_user_stake = if event.is_stake {
+                user_stake.checked_add(event.amount)
+            } else {
+                user_stake.checked_sub(event.amount)
+            };
+            if expected_user_stake != Some(event.total_user_stake) {
+                return Err(SoarError::UserStakeMismatch {
+                    index,
+                    expected: expected_user_stake,
+                    actual: event.total_user_stake,
+                });
+            }
+            user_stake = event.total_user_stake;
+            user_stake_hash = event.hash();
+        }
+        let to_user_stake_hash = self.claim.hashes().to_user_stake_event;
+        if user_stake_hash != to_user_stake_hash {
+            return Err(SoarError::EndpointMismatch {
+                chain: Chain::UserStake,
+                endpoint: Endpoint::To,
+                expected: to_user_stake_hash,
+                actual: user_stake_hash,
+            });
+        }
+        Ok(())
+    }
+
+    /// The user's stake and the hash of `claim.from_user_stake_event` after checking that it
+    /// is one of the user's events at or before the start of the stake segment.
+    pub(crate) fn initial_user_snapshot(&mut self) -> Result<(U256, Bytes32), SoarError> {
+        let event = match self.claim.from_user_stake_event.as_mut() {
+            Some(event) => event,
+            None => return Ok((U256::zero(), Bytes32::zero())),
+        };
+        if event.user != self.user {
+            return Err(SoarError::UserMismatch {
+                expected: self.user,
+                actual: event.user,
+            });
+        }
+        match &self.claim.from_stake_event {
+            Some(from_stake_event) if event.timestamp <= from_stake_event.timestamp => {}
+            _ => {
+                return Err(SoarError::UserSnapshotOutOfRange {
+                    endpoint: Endpoint::From,
+                })
+            }
+        }
+        Ok((event.total_user_stake, event.hash()))
+    }
+
+    /// The stake of every staker holding stake at the start of the stake segment, after
+    /// checking that `stake_history` is a contiguous segment of the stake chain ending at
+    /// `claim.from_stake_event`. The stakers' latest events in the history must add up to
+    /// the total stake at the start of the segment, so that no staker is left out.
+    pub fn initial_staker_stakes(&mut self) -> Result<BTreeMap<Address, U256>, SoarError> {
+        let mut previous_hash = None;
+        let mut previous_timestamp = U256::zero();
+        let mut stakes = BTreeMap::new();
+        for (index, event) in self.stake_history.iter_mut().enumerate() {
+            if let Some(previous_hash) = previous_hash {
+                if event.previous_event_hash != previous_hash {
+                    return Err(SoarError::ChainLink {
+                        chain: Chain::StakeHistory,
+                        index,
+                        expected: previous_hash,
+                        actual: event.previous_event_hash,
+                    });
                 }
             }
-            let reward_event = &self.reward_events[reward_event_index];
-            if total_stake != zero {
-                // Calculate the reward for the user at this point.
-                let user_reward =
-                    (total_user_stake * reward_event.amount * precision) / total_stake;
-                total_user_reward += user_reward;
+            if !event.verify_hash() {
+                return Err(SoarError::HashMismatch {
+                    chain: Chain::StakeHistory,
+                    index,
+                    supplied: event.current_event_hash,
+                    computed: event.hash(),
+                });
+            }
+            if event.timestamp < previous_timestamp {
+                return Err(SoarError::NonMonotonicTimestamp {
+                    chain: Chain::StakeHistory,
+                    index,
+                    previous: previous_timestamp,
+                    timestamp: event.timestamp,
+                });
             }
-            reward_event_index += 1;
+            previous_hash = Some(event.hash());
+            previous_timestamp = event.timestamp;
+            stakes.insert(event.user, event.total_user_stake);
+        }
+        let from_stake_hash = self.claim.hashes().from_stake_event;
+        if let Some(previous_hash) = previous_hash {
+            if previous_hash != from_stake_hash {
+                return Err(SoarError::EndpointMismatch {
+                    chain: Chain::StakeHistory,
+                    endpoint: Endpoint::To,
+                    expected: from_stake_hash,
+                    actual: previous_hash,
+                });
+            }
+        }
+        stakes.retain(|_, stake| !stake.is_zero());
+        let total_stake = stakes.values().try_fold(U256::zero(), |total, stake| {
+            total.checked_add(*stake).ok_or(SoarError::Overflow)
+        })?;
+        let initial_total_stake = self.initial_total_stake();
+        if total_stake != initial_total_stake {
+            return Err(SoarError::SnapshotTotalMismatch {
+                expected: initial_total_stake,
+                actual: total_stake,
+            });
         }
-        total_user_reward /= precision;
-        // At each reward event, get the user's total stake at that point.
-        total_user_reward
+        Ok(stakes)
+    }
+
+    /// Checks the stake and reward events against the claimed segments.
+    pub fn verify(&mut self) -> Result<(), SoarError> {
+        self.verify_chains()?;
+        self.verify_user_stake_chain()
+    }
+
+    /// Checks the stake and reward events against the claimed chain endpoints only, without
+    /// the user snapshots.
+    pub fn verify_chains(&mut self) -> Result<(), SoarError> {
+        self.verify_reward_chain()?;
+        self.verify_stake_chain()?;
+        self.verify_stake_coverage()
+    }
+
+    /// Checks that every reward event is later than the start of the stake segment. The
+    /// stake before an earlier reward event may differ from `from_stake_event`, and the
+    /// stake events setting it are not part of the segment.
+    fn verify_stake_coverage(&self) -> Result<(), SoarError> {
+        if let (Some(from_stake_event), Some(reward_event)) =
+            (&self.claim.from_stake_event, self.reward_events.first())
+        {
+            if reward_event.timestamp <= from_stake_event.timestamp {
+                return Err(SoarError::RewardBeforeStakeSegment { index: 0 });
+            }
+        }
+        Ok(())
+    }
+
+    /// Hash committing to the user and every endpoint of the claim:
+    /// `keccak256(abi.encodePacked(user, fromReward, toReward, fromStake, toStake,
+    /// fromUserStake, toUserStake))` with the zero hash for missing endpoints.
+    pub fn claim_digest(&mut self) -> Bytes32 {
+        let hashes = self.claim.hashes();
+        let mut packed = Vec::with_capacity(20 + 32 * 6);
+        packed.extend_from_slice(&self.user);
+        packed.extend_from_slice(&hashes.from_reward_event);
+        packed.extend_from_slice(&hashes.to_reward_event);
+        packed.extend_from_slice(&hashes.from_stake_event);
+        packed.extend_from_slice(&hashes.to_stake_event);
+        packed.extend_from_slice(&hashes.from_user_stake_event);
+        packed.extend_from_slice(&hashes.to_user_stake_event);
+        keccak256(&packed)
+    }
+
+    /// The user's stake at the start of the stake segment.
+    pub fn initial_user_stake(&self) -> U256 {
+        match &self.claim.from_user_stake_event {
+            Some(event) => event.total_user_stake,
+            None => U256::zero(),
+        }
+    }
+
+    /// The total stake at the start of the stake segment.
+    pub fn initial_total_stake(&self) -> U256 {
+        match &self.claim.from_stake_event {
+            Some(event) => event.total_staked,
+            None => U256::zero(),
+        }
+    }
+
+    pub fn calculate_reward(&mut self) -> Result<U256, SoarError> {
+        self.calculate_reward_with(&ReplayEngine::default())
+    }
+
+    /// Verifies the input and calculates the user's reward with the given engine. The reward
+    /// events must all distribute the same token, see `calculate_token_rewards` otherwise.
+    pub fn calculate_reward_with<E: RewardEngine>(
+        &mut self,
+        engine: &E,
+    ) -> Result<U256, SoarError> {
+        self.verify()?;
+        if self.reward_tokens().len() > 1 {
+            return Err(SoarError::MultipleRewardTokens);
+        }
+        engine.user_reward(self)
+    }
+
+    /// Tokens distributed by the reward events in order of first appearance, with the reward
+    /// chain's own reward token as the zero address.
+    pub fn reward_tokens(&self) -> Vec<Address> {
+        let mut tokens = Vec::new();
+        for event in &self.reward_events {
+            let token = event.reward_token();
+            if !tokens.contains(&token) {
+                tokens.push(token);
+            }
+        }
+        tokens
+    }
+
+    /// Verifies the input and calculates the user's reward in every token distributed by the
+    /// reward events, in the order of `reward_tokens`.
+    pub fn calculate_token_rewards(&mut self) -> Result<Vec<(Address, U256)>, SoarError> {
+        self.verify()?;
+        let engine = ReplayEngine::default();
+        self.reward_tokens()
+            .into_iter()
+            .map(|token| Ok((token, engine.token_distribution(self, &token)?.total_reward)))
+            .collect()
+    }
+
+    /// Calculates the user's reward with the given rounding policy, reporting the share of
+    /// every reward event and the dust left undistributed by rounding.
+    pub fn calculate_distribution(
+        &mut self,
+        policy: RoundingPolicy,
+    ) -> Result<RewardDistribution, SoarError> {
+        self.verify()?;
+        ReplayEngine { policy }.distribution(self)
+    }
+
+    /// Verifies the input and calculates the public values the program commits for the
+    /// claim. `total_rewards` is the reward in the reward chain's own token, the rewards in
+    /// every token are listed in `token_rewards`.
+    pub fn public_values(&mut self) -> Result<PublicValuesStruct, SoarError> {
+        let token_rewards = self.calculate_token_rewards()?;
+        let total_rewards = token_rewards
+            .iter()
+            .find(|(token, _)| *token == Address::default())
+            .map_or(U256::zero(), |(_, amount)| *amount);
+
+        let hashes = self.claim.hashes();
+        Ok(PublicValuesStruct {
+            user: self.user.into(),
+            total_rewards: total_rewards.into(),
+            from_reward_event_hash: hashes.from_reward_event.into(),
+            to_reward_event_hash: hashes.to_reward_event.into(),
+            from_stake_event_hash: hashes.from_stake_event.into(),
+            to_stake_event_hash: hashes.to_stake_event.into(),
+            from_user_stake_event_hash: hashes.from_user_stake_event.into(),
+            to_user_stake_event_hash: hashes.to_user_stake_event.into(),
+            updated_to_reward_event_hash: Bytes32::zero().into(),
+            token_rewards: token_rewards
+                .into_iter()
+                .map(|(token, amount)| TokenAmount {
+                    token: token.into(),
+                    amount: amount.into(),
+                })
+                .collect(),
+        })
     }
 }
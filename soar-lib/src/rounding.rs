@@ -0,0 +1,153 @@
+use crate::error::SoarError;
+use crate::types::{Bytes32, U256, U512};
+
+/// Number of sub-units per reward token unit used for the per event shares.
+pub const PRECISION: u64 = 1_000_000_000_000_000_000;
+
+/// How a user's fractional per event shares are turned into whole reward token units.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RoundingPolicy {
+    /// Every event's share is floored on its own; the fractions are lost at each event.
+    /// This is `amount * userStake / totalStaked` as the hardhat dataset script computes it.
+    #[default]
+    FloorPerEvent,
+    /// Shares are summed at full precision and floored once for the whole claim.
+    FloorAtEnd,
+    /// The fractional part is carried from event to event and a unit is paid out at the
+    /// event where the carried fractions add up to it. The claim total is the same as
+    /// `FloorAtEnd`, but every paid unit is attributed to a reward event.
+    CarryRemainder,
+}
+
+/// Rounding of a user's share of a single reward event.
+///
+/// Shares and remainders are in sub-units, `PRECISION` sub-units making one reward token
+/// unit, while `paid` is in reward token units. The shares are themselves floored to whole
+/// sub-units, so the remainders are relative to these scaled shares and do not include the
+/// fractions of a sub-unit dropped when computing them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RewardRounding {
+    pub reward_event_hash: Bytes32,
+    /// The user's stake and the total stake the event was shared out with.
+    pub user_stake: U256,
+    pub total_stake: U256,
+    /// The user's share of the event, `user_stake * amount * PRECISION / total_stake`.
+    pub share: U512,
+    /// Reward token units paid out at this event.
+    pub paid: U256,
+    /// With `FloorPerEvent` and `FloorAtEnd`, the fraction of this event's own share below
+    /// a whole unit, `share % PRECISION`. With `CarryRemainder`, the sub-units of the
+    /// user's shares up to this event that have not been paid out.
+    pub remainder: U512,
+}
+
+/// The rounded reward of a claim along with the dust left undistributed.
+///
+/// The dust is the claim user's only; the dust every staker leaves together is reported by
+/// `RewardLedger::dust`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RewardDistribution {
+    pub policy: RoundingPolicy,
+    /// Reward token units paid to the user for the whole claim.
+    pub total_reward: U256,
+    /// Sub-units of the user's shares that are not paid out by `total_reward`, see
+    /// `RewardRounding` for what the shares leave out.
+    pub remainder: U512,
+    pub events: Vec<RewardRounding>,
+}
+
+/// A reward event shared out among every staker, each share floored as with
+/// `RoundingPolicy::FloorPerEvent`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RewardDust {
+    pub reward_event_hash: Bytes32,
+    pub amount: U256,
+    /// Reward token units paid to the stakers, the sum of `amount * stake / total_stake`
+    /// floored for each of them.
+    pub paid: U256,
+    /// `amount - paid`, the units left undistributed. The whole amount when nobody holds
+    /// stake.
+    pub dust: U256,
+}
+
+/// Applies a `RoundingPolicy` to a sequence of per event shares.
+pub(crate) struct Rounder {
+    policy: RoundingPolicy,
+    precision: U512,
+    carried: U512,
+    total_paid: U256,
+    remainder: U512,
+    events: Vec<RewardRounding>,
+}
+
+impl Rounder {
+    pub(crate) fn new(policy: RoundingPolicy) -> Self {
+        Self {
+            policy,
+            precision: U512::from(PRECISION),
+            carried: U512::zero(),
+            total_paid: U256::zero(),
+            remainder: U512::zero(),
+            events: Vec::new(),
+        }
+    }
+
+    pub(crate) fn push(
+        &mut self,
+        reward_event_hash: Bytes32,
+        user_stake: U256,
+        total_stake: U256,
+        share: U512,
+    ) -> Result<(), SoarError> {
+        let (paid, remainder) = match self.policy {
+            RoundingPolicy::FloorPerEvent => {
+                let (paid, remainder) = share.div_mod(self.precision);
+                self.remainder = self
+                    .remainder
+                    .checked_add(remainder)
+                    .ok_or(SoarError::Overflow)?;
+                (U256::try_from(paid)?, remainder)
+            }
+            RoundingPolicy::FloorAtEnd => {
+                self.carried = self.carried.checked_add(share).ok_or(SoarError::Overflow)?;
+                (U256::zero(), share % self.precision)
+            }
+            RoundingPolicy::CarryRemainder => {
+                let carried = self.carried.checked_add(share).ok_or(SoarError::Overflow)?;
+                let (paid, carried) = carried.div_mod(self.precision);
+                self.carried = carried;
+                (U256::try_from(paid)?, carried)
+            }
+        };
+        self.total_paid = self
+            .total_paid
+            .checked_add(paid)
+            .ok_or(SoarError::Overflow)?;
+        self.events.push(RewardRounding {
+            reward_event_hash,
+            user_stake,
+            total_stake,
+            share,
+            paid,
+            remainder,
+        });
+        Ok(())
+    }
+
+    pub(crate) fn finish(self) -> Result<RewardDistribution, SoarError> {
+        let (total_reward, remainder) = match self.policy {
+            RoundingPolicy::FloorPerEvent => (self.total_paid, self.remainder),
+            RoundingPolicy::FloorAtEnd => {
+                let (paid, remainder) = self.carried.div_mod(self.precision);
+                (U256::try_from(paid)?, remainder)
+            }
+            RoundingPolicy::CarryRemainder => (self.total_paid, self.carried),
+        };
+        Ok(RewardDistribution {
+            policy: self.policy,
+            total_reward,
+            remainder,
+            events: self.events,
+        })
+    }
+}
@@ -0,0 +1,286 @@
+//! In-memory model of `StakeChain.sol` and `RewardChain.sol`, reproducing their state
+//! transitions and emitted events so that chains can be built without an EVM.
+//!
+//! `block.timestamp` is passed to every call, and the caller is responsible for keeping it
+//! non-decreasing as a chain would.
+
+use std::collections::BTreeMap;
+
+use crate::error::SoarError;
+use crate::reward::RewardChainExtendedEvent;
+use crate::stake::StakeChainExtendedEvent;
+use crate::types::{Address, Bytes32, Zero, U256};
+
+/// The user's stake, as stored in `StakeChain.stakes`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StakeSnapshot {
+    pub total_stake: U256,
+    /// Hash of the user's last stake event.
+    pub stake_chain_snapshot: Bytes32,
+}
+
+/// `StakeChain.sol`.
+#[derive(Clone, Debug, Default)]
+pub struct StakeChain {
+    pub total_staked: U256,
+    pub stake_chain: Bytes32,
+    pub stakes: BTreeMap<Address, StakeSnapshot>,
+    /// Every `StakeChainExtended` event emitted so far.
+    pub events: Vec<StakeChainExtendedEvent>,
+}
+
+impl StakeChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Extends the stake chain with an event of `user`, whose stake is already updated.
+    fn extend(&mut self, user: Address, is_stake: bool, amount: U256, timestamp: U256) {
+        let snapshot = self.stakes.entry(user).or_default();
+        let mut event = StakeChainExtendedEvent {
+            user,
+            is_stake,
+            amount,
+            total_staked: self.total_staked,
+            total_user_stake: snapshot.total_stake,
+            timestamp,
+            previous_event_hash: self.stake_chain,
+            current_event_hash: Bytes32::zero(),
+            hash: None,
+        };
+        self.stake_chain = event.hash();
+        event.current_event_hash = self.stake_chain;
+        snapshot.stake_chain_snapshot = self.stake_chain;
+        self.events.push(event);
+    }
+
+    /// `stake(amount)` sent by `user`.
+    pub fn stake(&mut self, user: Address, amount: U256, timestamp: U256) -> Result<(), SoarError> {
+        let total_stake = self
+            .stakes
+            .get(&user)
+            .copied()
+            .unwrap_or_default()
+            .total_stake;
+        let (total_staked, total_stake) = match (
+            self.total_staked.checked_add(amount),
+            total_stake.checked_add(amount),
+        ) {
+            (Some(total_staked), Some(total_stake)) => (total_staked, total_stake),
+            _ => return Err(SoarError::Reverted("arithmetic overflow")),
+        };
+        self.total_staked = total_staked;
+        self.stakes.entry(user).or_default().total_stake = total_stake;
+        self.extend(user, true, amount, timestamp);
+        Ok(())
+    }
+
+    /// `unstake(amount)` sent by `user`.
+    pub fn unstake(
+        &mut self,
+        user: Address,
+        amount: U256,
+        timestamp: U256,
+    ) -> Result<(), SoarError> {
+        let total_stake = self
+            .stakes
+            .get(&user)
+            .copied()
+            .unwrap_or_default()
+            .total_stake;
+        let (total_staked, total_stake) = match (
+            self.total_staked.checked_sub(amount),
+            total_stake.checked_sub(amount),
+        ) {
+            (Some(total_staked), Some(total_stake)) => (total_staked, total_stake),
+            _ => return Err(SoarError::Reverted("arithmetic underflow")),
+        };
+        self.total_staked = total_staked;
+        self.stakes.entry(user).or_default().total_stake = total_stake;
+        self.extend(user, false, amount, timestamp);
+        Ok(())
+    }
+
+    /// `stakeSnapshot(user)`: the user's stake and last stake event hash, and the current
+    /// stake chain hash.
+    pub fn stake_snapshot(&self, user: &Address) -> (U256, Bytes32, Bytes32) {
+        let snapshot = self.stakes.get(user).copied().unwrap_or_default();
+        (
+            snapshot.total_stake,
+            snapshot.stake_chain_snapshot,
+            self.stake_chain,
+        )
+    }
+}
+
+/// `RewardChain.HashInterval`, with an exclusive start and an inclusive end.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HashInterval {
+    pub start: Bytes32,
+    pub end: Bytes32,
+}
+
+/// `RewardChain.StakeRewardClaim`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StakeRewardClaim {
+    pub stake_interval: HashInterval,
+    pub reward_interval: HashInterval,
+    pub user_stake_interval: HashInterval,
+    pub reward_snapshot_time: U256,
+    pub stake_snapshot_time: U256,
+    pub claimed: bool,
+}
+
+/// `RewardChain.sol`.
+#[derive(Clone, Debug, Default)]
+pub struct RewardChain {
+    pub current_reward_chain: Bytes32,
+    pub total_rewards: U256,
+    pub total_token_rewards: BTreeMap<Address, U256>,
+    pub reward_begin_time: U256,
+    pub rewards: BTreeMap<Address, StakeRewardClaim>,
+    /// Every `RewardsAdded` and `TokenRewardsAdded` event emitted so far.
+    pub events: Vec<RewardChainExtendedEvent>,
+}
+
+impl RewardChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `relay(user, userStakeChainSnapshot, currentStakeChainSnapshot, stakeSnapshotTime)`.
+    ///
+    /// Like the contract, a claim whose stake interval starts at the beginning of the chain
+    /// is replaced rather than moved forward, so every relay of a user starts at the
+    /// beginning of the chains.
+    pub fn relay(
+        &mut self,
+        user: Address,
+        user_stake_chain_snapshot: Bytes32,
+        current_stake_chain_snapshot: Bytes32,
+        stake_snapshot_time: U256,
+        timestamp: U256,
+    ) -> Result<(), SoarError> {
+        let current_reward_chain = self.current_reward_chain;
+        let claim = self.rewards.entry(user).or_default();
+        if claim.stake_interval.start == Bytes32::zero() {
+            claim.stake_interval = HashInterval {
+                start: Bytes32::zero(),
+                end: current_stake_chain_snapshot,
+            };
+            claim.reward_interval = HashInterval {
+                start: Bytes32::zero(),
+                end: current_reward_chain,
+            };
+            claim.user_stake_interval = HashInterval {
+                start: Bytes32::zero(),
+                end: user_stake_chain_snapshot,
+            };
+        } else {
+            if !claim.claimed {
+                return Err(SoarError::Reverted(
+                    "There is a pending reward claim for the user",
+                ));
+            }
+            claim.stake_interval = HashInterval {
+                start: claim.stake_interval.end,
+                end: current_stake_chain_snapshot,
+            };
+            claim.claimed = false;
+            claim.reward_interval = HashInterval {
+                start: claim.reward_interval.end,
+                end: current_reward_chain,
+            };
+            claim.user_stake_interval = HashInterval {
+                start: claim.user_stake_interval.end,
+                end: user_stake_chain_snapshot,
+            };
+        }
+        claim.reward_snapshot_time = timestamp;
+        claim.stake_snapshot_time = stake_snapshot_time;
+        Ok(())
+    }
+
+    /// Relays the current snapshot of `user` on `stake_chain` as a relayer reading
+    /// `StakeChain.stakeSnapshot` would.
+    pub fn relay_snapshot(
+        &mut self,
+        stake_chain: &StakeChain,
+        user: Address,
+        timestamp: U256,
+    ) -> Result<(), SoarError> {
+        let (_, user_stake_chain_snapshot, current_stake_chain) = stake_chain.stake_snapshot(&user);
+        let stake_snapshot_time = stake_chain
+            .events
+            .last()
+            .map_or(U256::zero(), |event| event.timestamp);
+        self.relay(
+            user,
+            user_stake_chain_snapshot,
+            current_stake_chain,
+            stake_snapshot_time,
+            timestamp,
+        )
+    }
+
+    /// Extends the reward chain with `event`, whose hash is not computed yet.
+    fn extend(&mut self, mut event: RewardChainExtendedEvent) {
+        self.current_reward_chain = event.hash();
+        event.current_event_hash = self.current_reward_chain;
+        if self.reward_begin_time.is_zero() {
+            self.reward_begin_time = event.timestamp;
+        }
+        self.events.push(event);
+    }
+
+    /// `addRewards(amount)`.
+    pub fn add_rewards(&mut self, amount: U256, timestamp: U256) -> Result<(), SoarError> {
+        self.total_rewards = self
+            .total_rewards
+            .checked_add(amount)
+            .ok_or(SoarError::Reverted("arithmetic overflow"))?;
+        self.extend(RewardChainExtendedEvent {
+            token: None,
+            amount,
+            total_reward: self.total_rewards,
+            timestamp,
+            previous_event_hash: self.current_reward_chain,
+            current_event_hash: Bytes32::zero(),
+            hash: None,
+        });
+        Ok(())
+    }
+
+    /// `addTokenRewards(token, amount)`.
+    pub fn add_token_rewards(
+        &mut self,
+        token: Address,
+        amount: U256,
+        timestamp: U256,
+    ) -> Result<(), SoarError> {
+        if token == Address::default() {
+            return Err(SoarError::Reverted("Token rewards need a token address"));
+        }
+        let total = self.total_token_rewards.entry(token).or_default();
+        *total = total
+            .checked_add(amount)
+            .ok_or(SoarError::Reverted("arithmetic overflow"))?;
+        let total_reward = *total;
+        self.extend(RewardChainExtendedEvent {
+            token: Some(token),
+            amount,
+            total_reward,
+            timestamp,
+            previous_event_hash: self.current_reward_chain,
+            current_event_hash: Bytes32::zero(),
+            hash: None,
+        });
+        Ok(())
+    }
+
+    /// `claimRewards(proof, publicInputs)` sent by `user`. The contract does not verify the
+    /// proof yet and only marks the claim as claimed.
+    pub fn claim_rewards(&mut self, user: Address) {
+        self.rewards.entry(user).or_default().claimed = true;
+    }
+}
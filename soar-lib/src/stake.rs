@@ -1,21 +1,34 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::types::{Address, Bytes32, U256};
+use crate::error::SoarError;
+use crate::types::{
+    deserialize_bytes, hex_string, keccak256, parse_bytes, parse_u256, Address, Bytes32, U256,
+};
 
-#[derive(Clone, Debug, Deserialize)]
+/// Length of the `abi.encodePacked` payload hashed by `StakeChain.sol`.
+pub const STAKE_EVENT_PACKED_LEN: usize = 20 + 1 + 32 * 4 + 32;
+
+/// An event with its amounts as decimal strings and its address and hashes as hex strings.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct StakeChainExtendedEventJSON {
     pub user: String,
+    #[serde(rename = "isStake")]
     pub is_stake: bool,
     pub amount: String,
+    #[serde(rename = "totalStaked")]
     pub total_staked: String,
+    #[serde(rename = "totalUserStake")]
     pub total_user_stake: String,
     pub timestamp: String,
+    #[serde(rename = "previousStakeChain")]
     pub previous_event_hash: String,
+    #[serde(rename = "currentStakeChain")]
     pub current_event_hash: String,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct StakeChainExtendedEvent {
+    #[serde(deserialize_with = "deserialize_bytes")]
     pub user: Address,
     #[serde(rename = "isStake")]
     pub is_stake: bool,
@@ -25,58 +38,96 @@ pub struct StakeChainExtendedEvent {
     #[serde(rename = "totalUserStake")]
     pub total_user_stake: U256,
     pub timestamp: U256,
-    #[serde(rename = "previousStakeChain")]
+    #[serde(rename = "previousStakeChain", deserialize_with = "deserialize_bytes")]
     pub previous_event_hash: Bytes32,
-    #[serde(rename = "currentStakeChain")]
+    #[serde(rename = "currentStakeChain", deserialize_with = "deserialize_bytes")]
     pub current_event_hash: Bytes32,
 
-    hash: Option<Bytes32>,
+    /// Cache of `hash()`, not part of the serialized event.
+    #[serde(skip)]
+    pub(crate) hash: Option<Bytes32>,
 }
 
-impl From<StakeChainExtendedEventJSON> for StakeChainExtendedEvent {
-    fn from(event: StakeChainExtendedEventJSON) -> Self {
-        Self {
-            user: hex::decode(event.user).unwrap().try_into().unwrap(),
+impl TryFrom<StakeChainExtendedEventJSON> for StakeChainExtendedEvent {
+    type Error = SoarError;
+
+    fn try_from(event: StakeChainExtendedEventJSON) -> Result<Self, Self::Error> {
+        Ok(Self {
+            user: parse_bytes("user", &event.user)?,
             is_stake: event.is_stake,
-            amount: U256::from_dec_str(&event.amount).unwrap(),
-            total_staked: U256::from_dec_str(&event.total_staked).unwrap(),
-            total_user_stake: U256::from_dec_str(&event.total_user_stake).unwrap(),
-            timestamp: U256::from_dec_str(&event.timestamp).unwrap(),
-            previous_event_hash: hex::decode(event.previous_event_hash)
-                .unwrap()
-                .try_into()
-                .unwrap(),
-            current_event_hash: hex::decode(event.current_event_hash)
-                .unwrap()
-                .try_into()
-                .unwrap(),
+            amount: parse_u256("amount", &event.amount)?,
+            total_staked: parse_u256("totalStaked", &event.total_staked)?,
+            total_user_stake: parse_u256("totalUserStake", &event.total_user_stake)?,
+            timestamp: parse_u256("timestamp", &event.timestamp)?,
+            previous_event_hash: parse_bytes("previousStakeChain", &event.previous_event_hash)?,
+            current_event_hash: parse_bytes("currentStakeChain", &event.current_event_hash)?,
             hash: None,
+        })
+    }
+}
+
+impl From<&StakeChainExtendedEvent> for StakeChainExtendedEventJSON {
+    fn from(event: &StakeChainExtendedEvent) -> Self {
+        Self {
+            user: hex_string(&event.user),
+            is_stake: event.is_stake,
+            amount: event.amount.to_string(),
+            total_staked: event.total_staked.to_string(),
+            total_user_stake: event.total_user_stake.to_string(),
+            timestamp: event.timestamp.to_string(),
+            previous_event_hash: hex_string(&event.previous_event_hash),
+            current_event_hash: hex_string(&event.current_event_hash),
         }
     }
 }
 
 impl StakeChainExtendedEvent {
+    /// Packs the event the same way `StakeChain.stake` and `StakeChain.unstake` do before
+    /// hashing: `abi.encodePacked(user, isStake, amount, totalStaked, totalUserStake,
+    /// timestamp, previousStakeChain)`.
+    pub fn encode_packed(&self) -> Vec<u8> {
+        let mut packed = Vec::with_capacity(STAKE_EVENT_PACKED_LEN);
+        packed.extend_from_slice(&self.user);
+        packed.push(self.is_stake as u8);
+        packed.extend_from_slice(&self.amount.to_big_endian());
+        packed.extend_from_slice(&self.total_staked.to_big_endian());
+        packed.extend_from_slice(&self.total_user_stake.to_big_endian());
+        packed.extend_from_slice(&self.timestamp.to_big_endian());
+        packed.extend_from_slice(&self.previous_event_hash);
+        packed
+    }
+
+    /// Decodes an event from its `encode_packed` form. The current hash is the hash of
+    /// `packed`, which is cached right away.
+    pub fn decode_packed(packed: &[u8]) -> Option<Self> {
+        if packed.len() != STAKE_EVENT_PACKED_LEN || packed[20] > 1 {
+            return None;
+        }
+        let hash = keccak256(packed);
+        Some(Self {
+            user: packed[..20].try_into().unwrap(),
+            is_stake: packed[20] == 1,
+            amount: U256::from_big_endian(&packed[21..53]),
+            total_staked: U256::from_big_endian(&packed[53..85]),
+            total_user_stake: U256::from_big_endian(&packed[85..117]),
+            timestamp: U256::from_big_endian(&packed[117..149]),
+            previous_event_hash: packed[149..].try_into().unwrap(),
+            current_event_hash: hash,
+            hash: Some(hash),
+        })
+    }
+
+    /// Hash of the event as computed on chain. The result is cached, so the event
+    /// fields must not be modified after the first call.
     pub fn hash(&mut self) -> Bytes32 {
-        // TODO: Construct a hash from the event data.
         if self.hash.is_none() {
-            /*
-            let mut hasher = sha3::Keccak256::new();
-            hasher.update(&self.user);
-            hasher.update(&self.is_stake.to_bytes_be());
-            hasher.update(&self.amount.to_bytes_be());
-            hasher.update(&self.total_staked.to_bytes_be());
-            hasher.update(&self.total_user_stake.to_bytes_be());
-            hasher.update(&self.timestamp.to_bytes_be());
-            hasher.update(&self.previous_event_hash);
-            let result = hasher.finalize();
-            */
-            self.hash = Some(self.current_event_hash);
+            self.hash = Some(keccak256(&self.encode_packed()));
         }
         self.hash.unwrap()
     }
 
-    pub fn verify_hash(&mut self, expected: &Bytes32) -> bool {
-        // TODO: check if the constructed hash matches the one in the event.
-        self.hash() == *expected
+    /// Checks that the supplied `current_event_hash` matches the recomputed hash.
+    pub fn verify_hash(&mut self) -> bool {
+        self.hash() == self.current_event_hash
     }
 }
@@ -0,0 +1,344 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Chain, Endpoint, SoarError};
+use crate::reward::RewardChainExtendedEvent;
+use crate::reward_calculator::RewardCalculator;
+use crate::stake::StakeChainExtendedEvent;
+use crate::types::{deserialize_bytes, keccak256, mul_div, Address, Bytes32, Zero, U256};
+
+/// Length of the packed state hashed by `CalculatorState::digest`, without the accumulated
+/// rewards.
+pub const STATE_PACKED_LEN: usize = 20 + 32 * 4 + 32 * 5 + 32;
+
+/// Length of each packed accumulated reward following the rest of the state.
+pub const ACCUMULATED_REWARD_PACKED_LEN: usize = 20 + 32;
+
+/// The user's reward so far in one token.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccumulatedReward {
+    /// The token, with the reward chain's own reward token as the zero address.
+    #[serde(deserialize_with = "deserialize_bytes")]
+    pub token: Address,
+    pub amount: U256,
+}
+
+/// The progress of a reward calculation, so a claim can be calculated in several runs.
+///
+/// Every run is a `RewardCalculator` with the same claim and user whose events continue the
+/// stake and reward chains from the last verified hashes of the state. A run may stop after
+/// any number of reward events; stake events it supplies beyond the ones applied so far are
+/// simply supplied again by the next run. The share of every reward event is floored as with
+/// `RoundingPolicy::FloorPerEvent`, so the result is the one of
+/// `RewardCalculator::calculate_reward` whatever the chunks. Rewards are accumulated for
+/// every token in order of first appearance, as in `RewardCalculator::reward_tokens`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalculatorState {
+    #[serde(deserialize_with = "deserialize_bytes")]
+    pub user: Address,
+    /// Number of stake events of the segment applied so far.
+    pub stake_index: usize,
+    /// Number of reward events of the segment applied so far.
+    pub reward_index: usize,
+    pub total_stake: U256,
+    pub user_stake: U256,
+    /// The user's reward so far in every token distributed by the applied reward events.
+    pub accumulated_rewards: Vec<AccumulatedReward>,
+    /// Hash and timestamp of the last verified stake event, or of the start of the segment.
+    #[serde(deserialize_with = "deserialize_bytes")]
+    pub stake_hash: Bytes32,
+    pub stake_timestamp: U256,
+    /// Hash and timestamp of the last verified reward event, or of the start of the segment.
+    #[serde(deserialize_with = "deserialize_bytes")]
+    pub reward_hash: Bytes32,
+    pub reward_timestamp: U256,
+    /// Hash of the user's last stake event, the zero hash if the user never staked.
+    #[serde(deserialize_with = "deserialize_bytes")]
+    pub user_stake_hash: Bytes32,
+}
+
+impl TryFrom<Vec<u8>> for CalculatorState {
+    type Error = SoarError;
+
+    fn try_from(input: Vec<u8>) -> Result<Self, Self::Error> {
+        Ok(serde_json::from_slice(&input)?)
+    }
+}
+
+impl CalculatorState {
+    /// The state at the start of the calculator's claimed segments.
+    pub fn new(calculator: &mut RewardCalculator) -> Result<Self, SoarError> {
+        let (user_stake, user_stake_hash) = calculator.initial_user_snapshot()?;
+        let total_stake = calculator.initial_total_stake();
+        let claim = &mut calculator.claim;
+        let (stake_hash, stake_timestamp) = match claim.from_stake_event.as_mut() {
+            Some(event) => (event.hash(), event.timestamp),
+            None => (Bytes32::zero(), U256::zero()),
+        };
+        let (reward_hash, reward_timestamp) = match claim.from_reward_event.as_mut() {
+            Some(event) => (event.hash(), event.timestamp),
+            None => (Bytes32::zero(), U256::zero()),
+        };
+        Ok(Self {
+            user: calculator.user,
+            stake_index: 0,
+            reward_index: 0,
+            total_stake,
+            user_stake,
+            accumulated_rewards: Vec::new(),
+            stake_hash,
+            stake_timestamp,
+            reward_hash,
+            reward_timestamp,
+            user_stake_hash,
+        })
+    }
+
+    /// Verifies and applies the calculator's events, at most `max_reward_events` of its
+    /// reward events if given. A reward event is only applied once a later stake event has
+    /// been supplied or the stake segment is complete, so that no stake event preceding it
+    /// can be missing. Once the reward segment is complete, the remaining stake events are
+    /// applied as well.
+    pub fn advance(
+        &mut self,
+        calculator: &mut RewardCalculator,
+        max_reward_events: Option<usize>,
+    ) -> Result<(), SoarError> {
+        if calculator.user != self.user {
+            return Err(SoarError::UserMismatch {
+                expected: self.user,
+                actual: calculator.user,
+            });
+        }
+        let to_stake_hash = calculator.claim.to_stake_event.hash();
+        let reward_events = match max_reward_events {
+            Some(max) => max.min(calculator.reward_events.len()),
+            None => calculator.reward_events.len(),
+        };
+        let mut stake_events = calculator.stake_events.iter_mut();
+        let mut next_stake_event = stake_events.next();
+        for reward_event in &mut calculator.reward_events[..reward_events] {
+            self.verify_reward_event(reward_event)?;
+            loop {
+                match next_stake_event.as_deref_mut() {
+                    Some(stake_event) => {
+                        self.verify_stake_event(stake_event)?;
+                        if stake_event.timestamp >= reward_event.timestamp {
+                            break;
+                        }
+                        self.apply_stake_event(stake_event)?;
+                        next_stake_event = stake_events.next();
+                    }
+                    None if self.stake_hash == to_stake_hash => break,
+                    None => {
+                        return Err(SoarError::StakeEventsExhausted {
+                            index: self.reward_index,
+                        })
+                    }
+                }
+            }
+            self.apply_reward_event(reward_event)?;
+        }
+        if self.reward_hash == calculator.claim.to_reward_event.hash() {
+            while let Some(stake_event) = next_stake_event {
+                self.verify_stake_event(stake_event)?;
+                self.apply_stake_event(stake_event)?;
+                next_stake_event = stake_events.next();
+            }
+        }
+        Ok(())
+    }
+
+    /// Packs every field of the state, integers as big endian words:
+    /// `user, stakeIndex, rewardIndex, totalStake, userStake, stakeHash, stakeTimestamp,
+    /// rewardHash, rewardTimestamp, userStakeHash`, then the number of accumulated rewards
+    /// and the `token, amount` of each.
+    pub fn encode_packed(&self) -> Vec<u8> {
+        let mut packed = Vec::with_capacity(
+            STATE_PACKED_LEN + self.accumulated_rewards.len() * ACCUMULATED_REWARD_PACKED_LEN,
+        );
+        packed.extend_from_slice(&self.user);
+        packed.extend_from_slice(&U256::from(self.stake_index).to_big_endian());
+        packed.extend_from_slice(&U256::from(self.reward_index).to_big_endian());
+        packed.extend_from_slice(&self.total_stake.to_big_endian());
+        packed.extend_from_slice(&self.user_stake.to_big_endian());
+        packed.extend_from_slice(&self.stake_hash);
+        packed.extend_from_slice(&self.stake_timestamp.to_big_endian());
+        packed.extend_from_slice(&self.reward_hash);
+        packed.extend_from_slice(&self.reward_timestamp.to_big_endian());
+        packed.extend_from_slice(&self.user_stake_hash);
+        packed.extend_from_slice(&U256::from(self.accumulated_rewards.len()).to_big_endian());
+        for reward in &self.accumulated_rewards {
+            packed.extend_from_slice(&reward.token);
+            packed.extend_from_slice(&reward.amount.to_big_endian());
+        }
+        packed
+    }
+
+    /// Hash committing to the whole state, so that proofs of consecutive runs can be
+    /// chained by their start and end digests.
+    pub fn digest(&self) -> Bytes32 {
+        keccak256(&self.encode_packed())
+    }
+
+    /// Checks that the state reached the end of the calculator's claimed segments and
+    /// returns the user's reward in every token, as `RewardCalculator::calculate_token_rewards`.
+    pub fn finish(
+        &self,
+        calculator: &mut RewardCalculator,
+    ) -> Result<Vec<(Address, U256)>, SoarError> {
+        let hashes = calculator.claim.hashes();
+        let to_reward_hash = hashes.to_reward_event;
+        if self.reward_hash != to_reward_hash {
+            return Err(SoarError::EndpointMismatch {
+                chain: Chain::Reward,
+                endpoint: Endpoint::To,
+                expected: to_reward_hash,
+                actual: self.reward_hash,
+            });
+        }
+        let to_stake_hash = hashes.to_stake_event;
+        if self.stake_hash != to_stake_hash {
+            return Err(SoarError::EndpointMismatch {
+                chain: Chain::Stake,
+                endpoint: Endpoint::To,
+                expected: to_stake_hash,
+                actual: self.stake_hash,
+            });
+        }
+        let to_user_stake_hash = hashes.to_user_stake_event;
+        if self.user_stake_hash != to_user_stake_hash {
+            return Err(SoarError::EndpointMismatch {
+                chain: Chain::UserStake,
+                endpoint: Endpoint::To,
+                expected: to_user_stake_hash,
+                actual: self.user_stake_hash,
+            });
+        }
+        Ok(self
+            .accumulated_rewards
+            .iter()
+            .map(|reward| (reward.token, reward.amount))
+            .collect())
+    }
+
+    fn verify_reward_event(&self, event: &mut RewardChainExtendedEvent) -> Result<(), SoarError> {
+        let index = self.reward_index;
+        if event.previous_event_hash != self.reward_hash {
+            return Err(SoarError::ChainLink {
+                chain: Chain::Reward,
+                index,
+                expected: self.reward_hash,
+                actual: event.previous_event_hash,
+            });
+        }
+        if !event.verify_hash() {
+            return Err(SoarError::HashMismatch {
+                chain: Chain::Reward,
+                index,
+                supplied: event.current_event_hash,
+                computed: event.hash(),
+            });
+        }
+        if event.timestamp < self.reward_timestamp {
+            return Err(SoarError::NonMonotonicTimestamp {
+                chain: Chain::Reward,
+                index,
+                previous: self.reward_timestamp,
+                timestamp: event.timestamp,
+            });
+        }
+        // Applied stake events are always earlier than the reward event, so this only
+        // rejects reward events that are not later than `from_stake_event`.
+        if self.stake_hash != Bytes32::zero() && event.timestamp <= self.stake_timestamp {
+            return Err(SoarError::RewardBeforeStakeSegment { index });
+        }
+        Ok(())
+    }
+
+    fn verify_stake_event(&self, event: &mut StakeChainExtendedEvent) -> Result<(), SoarError> {
+        let index = self.stake_index;
+        if event.previous_event_hash != self.stake_hash {
+            return Err(SoarError::ChainLink {
+                chain: Chain::Stake,
+                index,
+                expected: self.stake_hash,
+                actual: event.previous_event_hash,
+            });
+        }
+        if !event.verify_hash() {
+            return Err(SoarError::HashMismatch {
+                chain: Chain::Stake,
+                index,
+                supplied: event.current_event_hash,
+                computed: event.hash(),
+            });
+        }
+        if event.timestamp < self.stake_timestamp {
+            return Err(SoarError::NonMonotonicTimestamp {
+                chain: Chain::Stake,
+                index,
+                previous: self.stake_timestamp,
+                timestamp: event.timestamp,
+            });
+        }
+        Ok(())
+    }
+
+    fn apply_stake_event(&mut self, event: &mut StakeChainExtendedEvent) -> Result<(), SoarError> {
+        if event.user == self.user {
+            let expected_user_stake = if event.is_stake {
+                self.user_stake.checked_add(event.amount)
+            } else {
+                self.user_stake.checked_sub(event.amount)
+            };
+            if expected_user_stake != Some(event.total_user_stake) {
+                return Err(SoarError::UserStakeMismatch {
+                    index: self.stake_index,
+                    expected: expected_user_stake,
+                    actual: event.total_user_stake,
+                });
+            }
+            self.user_stake = event.total_user_stake;
+            self.user_stake_hash = event.hash();
+        }
+        self.total_stake = event.total_staked;
+        self.stake_hash = event.hash();
+        self.stake_timestamp = event.timestamp;
+        self.stake_index += 1;
+        Ok(())
+    }
+
+    fn apply_reward_event(
+        &mut self,
+        event: &mut RewardChainExtendedEvent,
+    ) -> Result<(), SoarError> {
+        let token = event.reward_token();
+        let index = match self
+            .accumulated_rewards
+            .iter()
+            .position(|reward| reward.token == token)
+        {
+            Some(index) => index,
+            None => {
+                self.accumulated_rewards.push(AccumulatedReward {
+                    token,
+                    amount: U256::zero(),
+                });
+                self.accumulated_rewards.len() - 1
+            }
+        };
+        if !self.total_stake.is_zero() {
+            let share = mul_div(self.user_stake, event.amount, self.total_stake)?;
+            let accumulated = &mut self.accumulated_rewards[index];
+            accumulated.amount = accumulated
+                .amount
+                .checked_add(share)
+                .ok_or(SoarError::Overflow)?;
+        }
+        self.reward_hash = event.hash();
+        self.reward_timestamp = event.timestamp;
+        self.reward_index += 1;
+        Ok(())
+    }
+}
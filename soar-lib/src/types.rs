@@ -1,13 +1,71 @@
 use alloy_primitives::U256 as SolU256;
 use alloy_sol_types::sol;
-use serde::Deserialize;
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use sha3::{Digest, Keccak256};
+
+use crate::error::SoarError;
 
 construct_uint! {
-    /// 256-bit unsigned integer.
-    #[derive(Deserialize)]
+    /// 256-bit unsigned integer. Serialized as its little-endian `u64` limbs, deserialized
+    /// from the limbs or from a decimal or `0x`-prefixed hex string.
+    #[derive(Serialize)]
     pub struct U256(4);
 }
 
+construct_uint! {
+    /// 512-bit unsigned integer, wide enough for the product of two `U256` values.
+    #[derive(Serialize, Deserialize)]
+    pub struct U512(8);
+}
+
+impl From<U256> for U512 {
+    fn from(value: U256) -> Self {
+        let mut limbs = [0u64; 8];
+        limbs[..4].copy_from_slice(&value.0);
+        U512(limbs)
+    }
+}
+
+impl TryFrom<U512> for U256 {
+    type Error = SoarError;
+
+    fn try_from(value: U512) -> Result<Self, Self::Error> {
+        if value.0[4..].iter().any(|limb| *limb != 0) {
+            return Err(SoarError::Overflow);
+        }
+        let mut limbs = [0u64; 4];
+        limbs.copy_from_slice(&value.0[..4]);
+        Ok(U256(limbs))
+    }
+}
+
+/// `floor(a * b / denominator)` with a 512-bit intermediate product, exact whenever the
+/// result fits in 256 bits.
+pub fn mul_div(a: U256, b: U256, denominator: U256) -> Result<U256, SoarError> {
+    if denominator.is_zero() {
+        return Err(SoarError::DivisionByZero);
+    }
+    U256::try_from(U512::from(a) * U512::from(b) / U512::from(denominator))
+}
+
+/// `floor(a * b * scale / denominator)` with 512-bit intermediates. The quotient and the
+/// remainder of `a * b / denominator` are scaled separately, so the result is exact
+/// whenever it fits in 512 bits.
+pub fn mul_div_scaled(a: U256, b: U256, scale: U256, denominator: U256) -> Result<U512, SoarError> {
+    if denominator.is_zero() {
+        return Err(SoarError::DivisionByZero);
+    }
+    let denominator = U512::from(denominator);
+    let scale = U512::from(scale);
+    let (quotient, remainder) = (U512::from(a) * U512::from(b)).div_mod(denominator);
+    // remainder < denominator < 2^256, so remainder * scale cannot overflow.
+    quotient
+        .checked_mul(scale)
+        .and_then(|value| value.checked_add(remainder * scale / denominator))
+        .ok_or(SoarError::Overflow)
+}
+
 impl From<U256> for SolU256 {
     fn from(value: U256) -> Self {
         let mut bytes: [u8; 32] = [0; 32];
@@ -30,7 +88,158 @@ impl Zero for Bytes32 {
     }
 }
 
+/// Strips an optional `0x` prefix from a hex string.
+pub fn strip_hex_prefix(value: &str) -> &str {
+    value.strip_prefix("0x").unwrap_or(value)
+}
+
+/// Formats bytes as a `0x`-prefixed hex string.
+pub fn hex_string(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+/// Parses a decimal string into a `U256`.
+pub fn parse_u256(field: &'static str, value: &str) -> Result<U256, SoarError> {
+    U256::from_dec_str(value).map_err(|_| SoarError::Parse {
+        field,
+        value: value.to_string(),
+    })
+}
+
+/// Parses an optionally `0x`-prefixed hex string of exactly `N` bytes.
+pub fn parse_bytes<const N: usize>(field: &'static str, value: &str) -> Result<[u8; N], SoarError> {
+    hex::decode(strip_hex_prefix(value))
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| SoarError::Parse {
+            field,
+            value: value.to_string(),
+        })
+}
+
+/// Parses a decimal or `0x`-prefixed hex string into a `U256`.
+pub fn parse_u256_any(field: &'static str, value: &str) -> Result<U256, SoarError> {
+    match value.strip_prefix("0x") {
+        Some(digits) => U256::from_str_radix(digits, 16).map_err(|_| SoarError::Parse {
+            field,
+            value: value.to_string(),
+        }),
+        None => parse_u256(field, value),
+    }
+}
+
+struct U256Visitor;
+
+impl<'de> Visitor<'de> for U256Visitor {
+    type Value = U256;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "a decimal or 0x-prefixed hex string or an array of 4 u64 limbs"
+        )
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<U256, E> {
+        Ok(U256::from(value))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<U256, E> {
+        parse_u256_any("uint256", value).map_err(E::custom)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<U256, A::Error> {
+        let mut limbs = [0u64; 4];
+        for (index, limb) in limbs.iter_mut().enumerate() {
+            *limb = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(index, &self))?;
+        }
+        if seq.next_element::<u64>()?.is_some() {
+            return Err(de::Error::invalid_length(5, &self));
+        }
+        Ok(U256(limbs))
+    }
+}
+
+impl<'de> Deserialize<'de> for U256 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(U256Visitor)
+    }
+}
+
+/// Fixed-size byte array deserialized from an array of bytes or from a `0x`-prefixed hex
+/// string, see `deserialize_bytes`.
+struct FlexibleBytes<const N: usize>([u8; N]);
+
+struct BytesVisitor<const N: usize>;
+
+impl<'de, const N: usize> Visitor<'de> for BytesVisitor<N> {
+    type Value = FlexibleBytes<N>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a hex string or an array of {} bytes", N)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        parse_bytes("bytes", value)
+            .map(FlexibleBytes)
+            .map_err(E::custom)
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Self::Value, E> {
+        value
+            .try_into()
+            .map(FlexibleBytes)
+            .map_err(|_| de::Error::invalid_length(value.len(), &self))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = [0u8; N];
+        for (index, byte) in bytes.iter_mut().enumerate() {
+            *byte = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(index, &self))?;
+        }
+        if seq.next_element::<u8>()?.is_some() {
+            return Err(de::Error::invalid_length(N + 1, &self));
+        }
+        Ok(FlexibleBytes(bytes))
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for FlexibleBytes<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(BytesVisitor)
+    }
+}
+
+/// Deserializes an `Address` or `Bytes32` from an array of bytes or from a hex string, for
+/// use with `#[serde(deserialize_with)]`.
+pub fn deserialize_bytes<'de, D: Deserializer<'de>, const N: usize>(
+    deserializer: D,
+) -> Result<[u8; N], D::Error> {
+    FlexibleBytes::deserialize(deserializer).map(|bytes| bytes.0)
+}
+
+/// `deserialize_bytes` for optional fields.
+pub fn deserialize_optional_bytes<'de, D: Deserializer<'de>, const N: usize>(
+    deserializer: D,
+) -> Result<Option<[u8; N]>, D::Error> {
+    Option::<FlexibleBytes<N>>::deserialize(deserializer).map(|bytes| bytes.map(|bytes| bytes.0))
+}
+
+/// Keccak-256 digest, matching Solidity's `keccak256`.
+pub fn keccak256(data: &[u8]) -> Bytes32 {
+    Keccak256::digest(data).into()
+}
+
 sol! {
+    struct TokenAmount {
+        address token;
+        uint256 amount;
+    }
+
     struct PublicValuesStruct {
         address user;
         uint256 total_rewards;
@@ -41,5 +250,43 @@ sol! {
         bytes32 from_user_stake_event_hash;
         bytes32 to_user_stake_event_hash;
         bytes32 updated_to_reward_event_hash; // New field to enable updating to reward event hash based on the timestamp
+        TokenAmount[] token_rewards; // Reward per token, the reward chain's own token as the zero address
+    }
+}
+
+sol! {
+    struct RewardRootPublicValuesStruct {
+        bytes32 reward_root;
+        uint256 total_rewards;
+        bytes32 from_reward_event_hash;
+        bytes32 to_reward_event_hash;
+        bytes32 from_stake_event_hash;
+        bytes32 to_stake_event_hash;
+    }
+}
+
+sol! {
+    struct SegmentPublicValuesStruct {
+        bytes32 claim_digest;
+        bytes32 start_state_digest;
+        bytes32 end_state_digest;
+    }
+
+    struct AggregationPublicValuesStruct {
+        bytes32 segment_vkey; // Verifying key hash of the program proving the segments
+        PublicValuesStruct public_values;
     }
 }
+
+/// What the zkVM program commits for its input.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProgramOutput {
+    /// The claim user's reward, as `PublicValuesStruct`.
+    #[default]
+    UserReward,
+    /// The Merkle root of every staker's reward, as `RewardRootPublicValuesStruct`.
+    RewardRoot,
+    /// The digests of the `CalculatorState` before and after the input's events, as
+    /// `SegmentPublicValuesStruct`. The start state is read after the input.
+    Segment,
+}
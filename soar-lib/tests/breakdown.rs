@@ -0,0 +1,69 @@
+mod common;
+
+use soar_lib::breakdown::{format_share, RewardBreakdown, CSV_HEADER};
+use soar_lib::dataset::ExpectedOutput;
+use soar_lib::rounding::PRECISION;
+use soar_lib::types::{U256, U512};
+
+use common::{load_input, OUTPUT};
+
+#[test]
+fn shares_add_up_to_the_reward() {
+    let mut calculator = load_input();
+    let breakdown = RewardBreakdown::from_calculator(&mut calculator).unwrap();
+    assert_eq!(breakdown.events.len(), calculator.reward_events.len());
+
+    let paid: U512 = breakdown.events.iter().fold(U512::zero(), |total, event| {
+        total + event.share / U512::from(PRECISION)
+    });
+    let reward = calculator.calculate_reward().unwrap();
+    assert_eq!(paid, U512::from(reward));
+    for event in &breakdown.events {
+        assert!(event.user_stake <= event.total_stake);
+    }
+}
+
+#[test]
+fn json_matches_expected_output() {
+    let mut calculator = load_input();
+    let breakdown = RewardBreakdown::from_calculator(&mut calculator).unwrap();
+    let expected: ExpectedOutput = serde_json::from_slice(OUTPUT).unwrap();
+
+    // The expected output carries no shares, see `shares_add_up_to_the_reward` for those.
+    let without_shares = |mut json: serde_json::Value| {
+        for event in json.as_array_mut().unwrap() {
+            event.as_object_mut().unwrap().remove("userShare");
+        }
+        json
+    };
+    assert_eq!(
+        without_shares(serde_json::from_slice(&breakdown.to_json()).unwrap()),
+        without_shares(serde_json::to_value(&expected.user_reward_events).unwrap())
+    );
+}
+
+#[test]
+fn csv_has_a_row_per_reward_event() {
+    let mut calculator = load_input();
+    let breakdown = RewardBreakdown::from_calculator(&mut calculator).unwrap();
+    let csv = breakdown.to_csv();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], CSV_HEADER);
+    assert_eq!(lines.len(), breakdown.events.len() + 1);
+
+    let columns = CSV_HEADER.split(',').count();
+    for (line, event) in lines[1..].iter().zip(&breakdown.events) {
+        let fields: Vec<&str> = line.split(',').collect();
+        assert_eq!(fields.len(), columns);
+        assert_eq!(fields[1], event.reward_event.timestamp.to_string());
+        assert_eq!(fields[7], format_share(event.share));
+    }
+}
+
+#[test]
+fn formats_shares_with_18_decimals() {
+    assert_eq!(format_share(U512::zero()), "0.000000000000000000");
+    assert_eq!(format_share(U512::from(7)), "0.000000000000000007");
+    let share = U512::from(U256::from(3) * U256::from(PRECISION)) + U512::from(25);
+    assert_eq!(format_share(share), "3.000000000000000025");
+}
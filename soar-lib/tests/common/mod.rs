@@ -0,0 +1,42 @@
+//! Input shared by the integration tests. Each test crate uses a part of it only.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::Path;
+
+use soar_lib::reward_calculator::RewardCalculator;
+
+/// The claim of `data/input.json`.
+pub const INPUT: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/../data/input.json"));
+
+/// The expected result of `INPUT`, written by the hardhat dataset script.
+pub const OUTPUT: &[u8] =
+    include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/../data/output.json"));
+
+pub fn load_input() -> RewardCalculator {
+    RewardCalculator::try_from(INPUT.to_vec()).unwrap()
+}
+
+/// The `input.json` and `output.json` of every `data/seed-*` directory written by the dataset
+/// generator, with the directory's name, ordered by name.
+pub fn seed_fixtures() -> Vec<(String, Vec<u8>, Vec<u8>)> {
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("../data");
+    let mut fixtures: Vec<_> = fs::read_dir(data)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("seed-"))
+        })
+        .map(|path| {
+            (
+                path.file_name().unwrap().to_string_lossy().into_owned(),
+                fs::read(path.join("input.json")).unwrap(),
+                fs::read(path.join("output.json")).unwrap(),
+            )
+        })
+        .collect();
+    fixtures.sort();
+    fixtures
+}
@@ -0,0 +1,117 @@
+use soar_lib::dataset::{generate, DatasetParams, ExpectedOutput};
+use soar_lib::error::SoarError;
+use soar_lib::input::InputBuilder;
+use soar_lib::reward_calculator::RewardCalculator;
+use soar_lib::types::U256;
+
+fn params(seed: u64) -> DatasetParams {
+    DatasetParams {
+        seed,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn same_seed_gives_identical_files() {
+    let first = generate(&params(7)).unwrap();
+    let second = generate(&params(7)).unwrap();
+    assert_eq!(first.stakes_json(), second.stakes_json());
+    assert_eq!(first.rewards_json(), second.rewards_json());
+    assert_eq!(first.input_json(), second.input_json());
+    assert_eq!(first.output_json(), second.output_json());
+
+    let other = generate(&params(8)).unwrap();
+    assert_ne!(first.stakes_json(), other.stakes_json());
+}
+
+#[test]
+fn files_reproduce_the_expected_reward() {
+    for seed in 0..32 {
+        let dataset = generate(&params(seed)).unwrap();
+        let mut input = RewardCalculator::try_from(dataset.input_json()).unwrap();
+        // The expected output is taken from the simulated contract state, not the input.
+        let output = ExpectedOutput::from_calculator(&mut input).unwrap();
+        assert_eq!(
+            serde_json::to_string_pretty(&output).unwrap().as_bytes(),
+            dataset.output_json(),
+            "seed {}",
+            seed
+        );
+
+        // The dumps hold the full chains the claim was taken from.
+        let builder =
+            InputBuilder::from_json(&dataset.stakes_json(), &dataset.rewards_json()).unwrap();
+        assert_eq!(builder.stake_events.len(), dataset.stakes.events.len());
+        assert_eq!(builder.reward_events.len(), dataset.rewards.events.len());
+        for mut event in builder
+            .stake_events
+            .into_iter()
+            .chain(input.stake_history.iter().cloned())
+        {
+            assert!(event.verify_hash(), "seed {}", seed);
+        }
+    }
+}
+
+#[test]
+fn follows_the_parameters() {
+    let dataset = generate(&DatasetParams {
+        seed: 3,
+        users: 4,
+        iterations: 200,
+        stake_probability: 1.0,
+        unstake_probability: 0.0,
+        reward_probability: 1.0,
+        min_time_increase: 10,
+        max_time_increase: 20,
+        ..Default::default()
+    })
+    .unwrap();
+    let events = &dataset.stakes.events;
+    assert!(events.iter().all(|event| event.is_stake));
+    assert!(dataset.stakes.stakes.len() <= 4);
+    assert_eq!(events.len() + dataset.rewards.events.len(), 200);
+    let timestamps: Vec<U256> = events.iter().map(|event| event.timestamp).collect();
+    assert!(timestamps
+        .windows(2)
+        .all(|pair| pair[1] - pair[0] >= U256::from(10)));
+}
+
+#[test]
+fn rejects_invalid_parameters() {
+    let invalid = |params: DatasetParams| match generate(&params) {
+        Err(SoarError::InvalidDatasetParams(_)) => {}
+        other => panic!("{:?}", other.map(|dataset| dataset.output)),
+    };
+    invalid(DatasetParams {
+        users: 0,
+        ..params(1)
+    });
+    invalid(DatasetParams {
+        stake_probability: 0.0,
+        unstake_probability: 0.0,
+        reward_probability: 0.0,
+        ..params(1)
+    });
+    invalid(DatasetParams {
+        reward_probability: f64::NAN,
+        ..params(1)
+    });
+    invalid(DatasetParams {
+        min_time_increase: 0,
+        ..params(1)
+    });
+    invalid(DatasetParams {
+        min_time_increase: 10,
+        max_time_increase: 5,
+        ..params(1)
+    });
+    invalid(DatasetParams {
+        max_stake: 0,
+        ..params(1)
+    });
+    invalid(DatasetParams {
+        iterations: 0,
+        ..params(1)
+    });
+}
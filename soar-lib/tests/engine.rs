@@ -0,0 +1,111 @@
+mod common;
+
+use common::load_input;
+use num_bigint::BigUint;
+use soar_lib::engine::{AccumulatorEngine, ReplayEngine, RewardEngine, RewardIndex};
+use soar_lib::reward_calculator::RewardCalculator;
+use soar_lib::rounding::RoundingPolicy;
+use soar_lib::types::U256;
+
+const FLOOR_AT_END: ReplayEngine = ReplayEngine {
+    policy: RoundingPolicy::FloorAtEnd,
+};
+
+fn to_big(value: U256) -> BigUint {
+    BigUint::from_bytes_be(&value.to_big_endian())
+}
+
+/// The user's exact reward floored, summing the fractions of every reward event's share.
+fn exact_reward(calculator: &RewardCalculator) -> U256 {
+    let distribution = FLOOR_AT_END.distribution(calculator).unwrap();
+    let mut numerator = BigUint::from(0u8);
+    let mut denominator = BigUint::from(1u8);
+    for (rounding, event) in distribution.events.iter().zip(&calculator.reward_events) {
+        if rounding.total_stake.is_zero() {
+            continue;
+        }
+        let total_stake = to_big(rounding.total_stake);
+        numerator = numerator * &total_stake
+            + to_big(rounding.user_stake) * to_big(event.amount) * &denominator;
+        denominator *= total_stake;
+    }
+    U256::from_big_endian(&(numerator / denominator).to_bytes_be())
+}
+
+/// The accumulator pays the exact reward floored or one unit less, as the replay does when
+/// flooring once at the end.
+fn assert_within_one_unit(calculator: &RewardCalculator, reward: U256) {
+    let exact = exact_reward(calculator);
+    let replay = FLOOR_AT_END.user_reward(calculator).unwrap();
+    for reward in [reward, replay] {
+        assert!(reward <= exact && exact - reward <= U256::one());
+    }
+}
+
+#[test]
+fn engines_agree_on_input() {
+    let mut calculator = load_input();
+    let replay = calculator.calculate_reward_with(&FLOOR_AT_END).unwrap();
+    assert_eq!(
+        calculator.calculate_reward_with(&AccumulatorEngine),
+        Ok(replay)
+    );
+}
+
+#[test]
+fn engines_agree_for_user_with_events_in_window() {
+    let mut calculator = load_input();
+    // The user of stake event 10 stakes for the first time and unstakes in stake event 13.
+    calculator.user = calculator.stake_events[10].user;
+    calculator.claim.user = calculator.user;
+    calculator.claim.from_user_stake_event = None;
+    calculator.claim.to_user_stake_event = Some(calculator.stake_events[13].clone());
+    let reward = calculator
+        .calculate_reward_with(&AccumulatorEngine)
+        .unwrap();
+    assert_within_one_unit(&calculator, reward);
+}
+
+#[test]
+fn shared_index_is_within_one_unit_for_every_staker() {
+    let mut calculator = load_input();
+    calculator.verify().unwrap();
+    let index = RewardIndex::build(
+        calculator.initial_total_stake(),
+        &calculator.stake_events,
+        &calculator.reward_events,
+    )
+    .unwrap();
+    let users: Vec<_> = calculator
+        .stake_events
+        .iter()
+        .map(|event| event.user)
+        .collect();
+    for user in users {
+        let mut claim = calculator.clone();
+        claim.user = user;
+        claim.claim.from_user_stake_event = calculator
+            .stake_history
+            .iter()
+            .rev()
+            .find(|event| event.user == user)
+            .cloned();
+        let reward = index
+            .user_reward(&user, claim.initial_user_stake(), &calculator.stake_events)
+            .unwrap();
+        assert_within_one_unit(&claim, reward);
+    }
+}
+
+#[test]
+fn index_floors_an_exact_share_one_unit_short() {
+    let mut calculator = load_input();
+    calculator.reward_events.truncate(1);
+    calculator.reward_events[0].amount = U256::one();
+    // Each of three stake units earns a third of the unit, which the index floors.
+    let index = RewardIndex::build(U256::from(3), &[], &calculator.reward_events).unwrap();
+    let reward = index
+        .user_reward(&calculator.user, U256::from(3), &[])
+        .unwrap();
+    assert_eq!(reward, U256::zero());
+}
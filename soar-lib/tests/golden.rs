@@ -0,0 +1,122 @@
+//! Runs the calculator on every input/output fixture pair and checks the total and every
+//! reward event's stakes and share against the expected output: `data/` written by the
+//! hardhat dataset script, and every `data/seed-*` written by the dataset generator from the
+//! simulated contract state. Both compute the expected result independently of the
+//! calculator.
+//!
+//! The hardhat script started the stake segment of `data/` after five of the ten claimed
+//! reward events, so the stakes they were shared out with were not part of the input. Its
+//! stake segment starts at the last stake event before the reward segment instead, in
+//! `data/input.json` and in the stake endpoint of `data/output.json`.
+
+mod common;
+
+use common::{load_input, seed_fixtures, INPUT, OUTPUT};
+use soar_lib::breakdown::UserRewardEvent;
+use soar_lib::dataset::ExpectedOutput;
+use soar_lib::reward_calculator::RewardCalculator;
+use soar_lib::types::{parse_bytes, Address, Bytes32};
+
+fn reward_hash(event: &UserRewardEvent) -> Bytes32 {
+    parse_bytes("currentRewardChain", &event.reward_event.current_event_hash).unwrap()
+}
+
+/// Describes the first difference between the expected and the calculated result.
+fn divergence(expected: &ExpectedOutput, actual: &ExpectedOutput) -> Option<String> {
+    let expected_user: Address = parse_bytes("user", &expected.user).unwrap();
+    let actual_user: Address = parse_bytes("user", &actual.user).unwrap();
+    if expected_user != actual_user {
+        return Some(format!("user {} instead of {}", actual.user, expected.user));
+    }
+    for (index, (expected, actual)) in expected
+        .user_reward_events
+        .iter()
+        .zip(&actual.user_reward_events)
+        .enumerate()
+    {
+        if reward_hash(expected) != reward_hash(actual)
+            || expected.user_stake != actual.user_stake
+            || expected.total_staked != actual.total_staked
+        {
+            return Some(format!(
+                "reward event {} is {} with user stake {} of {}, expected {} with user stake {} of {}",
+                index,
+                actual.reward_event.current_event_hash,
+                actual.user_stake,
+                actual.total_staked,
+                expected.reward_event.current_event_hash,
+                expected.user_stake,
+                expected.total_staked
+            ));
+        }
+        // The hardhat script does not write the shares.
+        if !expected.user_share.is_empty() && expected.user_share != actual.user_share {
+            return Some(format!(
+                "reward event {} gives a share of {}, expected {}",
+                index, actual.user_share, expected.user_share
+            ));
+        }
+    }
+    if expected.user_reward_events.len() != actual.user_reward_events.len() {
+        return Some(format!(
+            "{} reward events instead of {}",
+            actual.user_reward_events.len(),
+            expected.user_reward_events.len()
+        ));
+    }
+    if expected.total_rewards != actual.total_rewards {
+        return Some(format!(
+            "total rewards {} instead of {}",
+            actual.total_rewards, expected.total_rewards
+        ));
+    }
+    None
+}
+
+#[test]
+fn fixtures_match_expected_output() {
+    let seeds = seed_fixtures();
+    assert!(!seeds.is_empty());
+    let fixtures = [("data".to_string(), INPUT.to_vec(), OUTPUT.to_vec())]
+        .into_iter()
+        .chain(seeds);
+    for (name, input, output) in fixtures {
+        let expected: ExpectedOutput = serde_json::from_slice(&output).unwrap();
+        let mut calculator = RewardCalculator::try_from(input).unwrap();
+        let actual = ExpectedOutput::from_calculator(&mut calculator).unwrap();
+        if let Some(divergence) = divergence(&expected, &actual) {
+            panic!("{}: {}", name, divergence);
+        }
+        let public_values = calculator.public_values().unwrap();
+        assert_eq!(
+            public_values.total_rewards.to_string(),
+            expected.total_rewards,
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn reports_first_divergent_reward_event() {
+    let mut calculator = load_input();
+    let actual = ExpectedOutput::from_calculator(&mut calculator).unwrap();
+
+    let mut expected = actual.clone();
+    expected.user_reward_events[2].total_staked = "1".to_string();
+    expected.user_reward_events[3].user_stake = "1".to_string();
+    expected.total_rewards = "1".to_string();
+    let message = divergence(&expected, &actual).unwrap();
+    assert!(message.starts_with("reward event 2 "), "{}", message);
+
+    let mut expected = actual.clone();
+    expected.user_reward_events.pop();
+    assert_eq!(
+        divergence(&expected, &actual).unwrap(),
+        format!(
+            "{} reward events instead of {}",
+            actual.user_reward_events.len(),
+            expected.user_reward_events.len()
+        )
+    );
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use common::load_input;
+use soar_lib::error::{Chain, SoarError};
+use soar_lib::input::{EventSelector, InputBuilder, SegmentSelection};
+use soar_lib::reward_calculator::RewardCalculator;
+use soar_lib::types::{Bytes32, U256};
+
+const STAKES: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/../data/stakes.json"));
+const REWARDS: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/../data/rewards.json"));
+
+fn load_builder() -> InputBuilder {
+    InputBuilder::from_json(STAKES, REWARDS).unwrap()
+}
+
+fn hash_selection(input: &RewardCalculator) -> SegmentSelection {
+    SegmentSelection {
+        from_stake: input
+            .claim
+            .from_stake_event
+            .as_ref()
+            .map(|event| EventSelector::Hash(event.current_event_hash)),
+        to_stake: EventSelector::Hash(input.claim.to_stake_event.current_event_hash),
+        from_reward: input
+            .claim
+            .from_reward_event
+            .as_ref()
+            .map(|event| EventSelector::Hash(event.current_event_hash)),
+        to_reward: EventSelector::Hash(input.claim.to_reward_event.current_event_hash),
+    }
+}
+
+fn stake_hashes(calculator: &RewardCalculator) -> Vec<Bytes32> {
+    calculator
+        .stake_events
+        .iter()
+        .map(|event| event.current_event_hash)
+        .collect()
+}
+
+fn reward_hashes(calculator: &RewardCalculator) -> Vec<Bytes32> {
+    calculator
+        .reward_events
+        .iter()
+        .map(|event| event.current_event_hash)
+        .collect()
+}
+
+#[test]
+fn rebuilds_input_from_claim_hashes() {
+    let mut input = load_input();
+    let mut built = load_builder()
+        .build(input.user, hash_selection(&input))
+        .unwrap();
+
+    assert_eq!(stake_hashes(&built), stake_hashes(&input));
+    assert_eq!(reward_hashes(&built), reward_hashes(&input));
+    assert_eq!(built.claim_digest(), input.claim_digest());
+    assert_eq!(
+        built.calculate_reward().unwrap(),
+        input.calculate_reward().unwrap()
+    );
+
+    let history = |calculator: &RewardCalculator| -> Vec<Bytes32> {
+        calculator
+            .stake_history
+            .iter()
+            .map(|event| event.current_event_hash)
+            .collect()
+    };
+    assert_eq!(history(&built), history(&input));
+}
+
+#[test]
+fn timestamps_select_latest_event_at_or_before() {
+    let input = load_input();
+    let builder = load_builder();
+    let from_stake = input.claim.from_stake_event.as_ref().unwrap();
+    let from_reward = input.claim.from_reward_event.as_ref().unwrap();
+    let selection = SegmentSelection {
+        from_stake: Some(EventSelector::Timestamp(from_stake.timestamp)),
+        to_stake: EventSelector::Timestamp(input.claim.to_stake_event.timestamp),
+        from_reward: Some(EventSelector::Timestamp(from_reward.timestamp)),
+        to_reward: EventSelector::Timestamp(input.claim.to_reward_event.timestamp),
+    };
+    let built = builder.build(input.user, selection).unwrap();
+    assert_eq!(
+        built.claim.to_stake_event.current_event_hash,
+        input.claim.to_stake_event.current_event_hash
+    );
+    assert_eq!(
+        built.claim.to_reward_event.current_event_hash,
+        input.claim.to_reward_event.current_event_hash
+    );
+    assert_eq!(stake_hashes(&built), stake_hashes(&input));
+    assert_eq!(reward_hashes(&built), reward_hashes(&input));
+}
+
+#[test]
+fn selects_from_start_of_chains() {
+    let input = load_input();
+    let builder = load_builder();
+    let mut selection = hash_selection(&input);
+    selection.from_stake = None;
+    selection.from_reward = Some(EventSelector::Hash(Bytes32::default()));
+    let built = builder.build(input.user, selection).unwrap();
+    assert!(built.claim.from_stake_event.is_none());
+    assert!(built.claim.from_reward_event.is_none());
+    assert!(built.claim.from_user_stake_event.is_none());
+    assert!(built.stake_history.is_empty());
+    assert_eq!(
+        built.stake_events[0].current_event_hash,
+        builder.stake_events[0].current_event_hash
+    );
+}
+
+#[test]
+fn rejects_unknown_hash() {
+    let input = load_input();
+    let mut selection = hash_selection(&input);
+    selection.to_reward = EventSelector::Hash([0x11; 32]);
+    assert_eq!(
+        load_builder().build(input.user, selection).unwrap_err(),
+        SoarError::UnknownEvent {
+            chain: Chain::Reward,
+            hash: [0x11; 32]
+        }
+    );
+}
+
+#[test]
+fn rejects_timestamp_before_first_event() {
+    let input = load_input();
+    let mut selection = hash_selection(&input);
+    selection.to_stake = EventSelector::Timestamp(U256::one());
+    assert_eq!(
+        load_builder().build(input.user, selection).unwrap_err(),
+        SoarError::NoEventAt {
+            chain: Chain::Stake,
+            timestamp: U256::one()
+        }
+    );
+}
+
+#[test]
+fn rejects_reversed_segment() {
+    let input = load_input();
+    let mut selection = hash_selection(&input);
+    selection.from_stake = Some(selection.to_stake);
+    selection.to_stake = EventSelector::Hash(
+        input
+            .claim
+            .from_stake_event
+            .as_ref()
+            .unwrap()
+            .current_event_hash,
+    );
+    assert_eq!(
+        load_builder().build(input.user, selection).unwrap_err(),
+        SoarError::ReversedSegment {
+            chain: Chain::Stake
+        }
+    );
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use common::load_input;
+use std::collections::BTreeSet;
+
+use soar_lib::error::SoarError;
+use soar_lib::ledger::RewardLedger;
+use soar_lib::types::U256;
+
+#[test]
+fn matches_calculator_for_claim_user() {
+    let mut calculator = load_input();
+    let ledger = RewardLedger::from_calculator(&mut calculator).unwrap();
+    let entry = ledger.entry(&calculator.user).unwrap();
+    assert_eq!(entry.reward, calculator.calculate_reward().unwrap());
+    assert_eq!(entry.stake, calculator.initial_user_stake());
+}
+
+#[test]
+fn matches_calculator_for_every_staker() {
+    let mut calculator = load_input();
+    let ledger = RewardLedger::from_stake_history(&mut calculator).unwrap();
+    let entries = ledger.entries();
+    let users: BTreeSet<_> = calculator
+        .stake_events
+        .iter()
+        .map(|event| event.user)
+        .collect();
+    for user in users {
+        let mut claim = calculator.clone();
+        claim.user = user;
+        claim.claim.user = user;
+        claim.claim.from_user_stake_event = calculator
+            .stake_history
+            .iter()
+            .rev()
+            .find(|event| event.user == user)
+            .cloned();
+        claim.claim.to_user_stake_event = claim
+            .stake_events
+            .iter()
+            .rev()
+            .find(|event| event.user == user)
+            .cloned();
+        let entry = entries[&user];
+        assert_eq!(entry.reward, claim.calculate_reward().unwrap());
+        assert_eq!(
+            entry.stake,
+            claim.claim.to_user_stake_event.unwrap().total_user_stake
+        );
+    }
+}
+
+#[test]
+fn does_not_distribute_more_than_rewarded() {
+    let mut calculator = load_input();
+    let ledger = RewardLedger::from_calculator(&mut calculator).unwrap();
+    let distributed = ledger
+        .entries()
+        .values()
+        .fold(U256::zero(), |total, entry| total + entry.reward);
+    let rewarded = calculator
+        .reward_events
+        .iter()
+        .fold(U256::zero(), |total, event| total + event.amount);
+    assert!(distributed <= rewarded);
+    assert_eq!(
+        ledger.total_stake(),
+        calculator.claim.to_stake_event.total_staked
+    );
+}
+
+#[test]
+fn rejects_broken_chain() {
+    let mut calculator = load_input();
+    calculator.stake_events.remove(1);
+    assert!(RewardLedger::from_calculator(&mut calculator).is_err());
+}
+
+#[test]
+fn floors_each_reward_event_like_the_calculator() {
+    let mut reward_event = load_input().reward_events[0].clone();
+    reward_event.amount = U256::one();
+    let (user, other) = ([1; 20], [2; 20]);
+    let mut ledger = RewardLedger::new(U256::from(3))
+        .with_user_stake(user, U256::from(2))
+        .with_user_stake(other, U256::from(1));
+    ledger
+        .apply(&[], &[reward_event.clone(), reward_event])
+        .unwrap();
+    // Two thirds of a unit at each event are floored away.
+    assert_eq!(ledger.entry(&user).unwrap().reward, U256::zero());
+}
+
+#[test]
+fn rejects_mixed_reward_tokens() {
+    let calculator = load_input();
+    let mut reward_events = calculator.reward_events.clone();
+    reward_events[1].token = Some([1; 20]);
+    let mut ledger = RewardLedger::new(calculator.initial_total_stake())
+        .with_user_stake(calculator.user, calculator.initial_user_stake());
+    let before = ledger.clone();
+    assert_eq!(
+        ledger.apply(&calculator.stake_events, &reward_events),
+        Err(SoarError::MultipleRewardTokens)
+    );
+    assert_eq!(ledger, before);
+}
@@ -0,0 +1,208 @@
+use alloy_sol_types::private::{Address, FixedBytes};
+use alloy_sol_types::SolEvent;
+use soar_lib::error::SoarError;
+use soar_lib::logs::{
+    reward_events, stake_events, LoggedEvent, RawLog, RewardsAdded, StakeChainExtended,
+    TokenRewardsAdded,
+};
+use soar_lib::reward::RewardChainExtendedEvent;
+use soar_lib::stake::StakeChainExtendedEvent;
+use soar_lib::types::keccak256;
+
+const STAKES: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/../data/stakes.json"));
+const REWARDS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/../data/rewards.json"));
+
+const STAKE_CHAIN: [u8; 20] = [0x5f; 20];
+
+const REWARD_CHAIN: [u8; 20] = [0xe7; 20];
+
+fn load_stakes() -> Vec<StakeChainExtendedEvent> {
+    serde_json::from_str(STAKES).unwrap()
+}
+
+fn load_rewards() -> Vec<RewardChainExtendedEvent> {
+    serde_json::from_str(REWARDS).unwrap()
+}
+
+/// A log as the contract at `address` emits it for `event`, the `index`th log of block
+/// `index / 3`.
+fn raw_log(address: [u8; 20], event: &impl SolEvent, index: u64) -> RawLog {
+    let log = event.encode_log_data();
+    RawLog {
+        address,
+        topics: log.topics().iter().map(|topic| topic.0).collect(),
+        data: log.data.to_vec(),
+        block_number: index / 3,
+        transaction_hash: keccak256(&index.to_be_bytes()),
+        log_index: index % 3,
+    }
+}
+
+fn stake_log(event: &StakeChainExtendedEvent, index: u64) -> RawLog {
+    raw_log(
+        STAKE_CHAIN,
+        &StakeChainExtended {
+            user: Address::from(event.user),
+            isStake: event.is_stake,
+            amount: event.amount.into(),
+            totalStaked: event.total_staked.into(),
+            totalUserStake: event.total_user_stake.into(),
+            timestamp: event.timestamp.into(),
+            previous: FixedBytes(event.previous_event_hash),
+            current: FixedBytes(event.current_event_hash),
+        },
+        index,
+    )
+}
+
+fn reward_log(event: &RewardChainExtendedEvent, index: u64) -> RawLog {
+    raw_log(
+        REWARD_CHAIN,
+        &RewardsAdded {
+            amount: event.amount.into(),
+            totalRewards: event.total_reward.into(),
+            timestamp: event.timestamp.into(),
+            previousRewardChain: FixedBytes(event.previous_event_hash),
+            currentRewardChain: FixedBytes(event.current_event_hash),
+        },
+        index,
+    )
+}
+
+#[test]
+fn signatures_match_contract_events() {
+    assert_eq!(
+        StakeChainExtended::SIGNATURE_HASH.0,
+        keccak256(
+            b"StakeChainExtended(address,bool,uint256,uint256,uint256,uint256,bytes32,bytes32)"
+        )
+    );
+    assert_eq!(
+        RewardsAdded::SIGNATURE_HASH.0,
+        keccak256(b"RewardsAdded(uint256,uint256,uint256,bytes32,bytes32)")
+    );
+    assert_eq!(
+        TokenRewardsAdded::SIGNATURE_HASH.0,
+        keccak256(b"TokenRewardsAdded(address,uint256,uint256,uint256,bytes32,bytes32)")
+    );
+}
+
+#[test]
+fn decodes_stake_logs_in_chain_order() {
+    let stakes = load_stakes();
+    let mut logs: Vec<RawLog> = stakes
+        .iter()
+        .enumerate()
+        .map(|(index, event)| stake_log(event, index as u64))
+        .collect();
+    logs.reverse();
+    // Logs of other events and of other contracts are skipped.
+    logs.push(reward_log(&load_rewards()[0], 1000));
+    let mut other_contract = stake_log(&stakes[0], 1001);
+    other_contract.address = [0x66; 20];
+    logs.push(other_contract);
+
+    let decoded = stake_events(&logs, &STAKE_CHAIN).unwrap();
+    assert_eq!(decoded.len(), stakes.len());
+    for (index, (logged, event)) in decoded.into_iter().zip(&stakes).enumerate() {
+        let mut decoded_event = logged.event;
+        assert!(decoded_event.verify_hash());
+        assert_eq!(decoded_event.encode_packed(), event.encode_packed());
+        assert_eq!(decoded_event.current_event_hash, event.current_event_hash);
+        assert_eq!(logged.block_number, index as u64 / 3);
+        assert_eq!(logged.log_index, index as u64 % 3);
+        assert_eq!(
+            logged.transaction_hash,
+            keccak256(&(index as u64).to_be_bytes())
+        );
+    }
+}
+
+#[test]
+fn decodes_reward_and_token_reward_logs() {
+    let rewards = load_rewards();
+    let mut logs: Vec<RawLog> = rewards
+        .iter()
+        .enumerate()
+        .map(|(index, event)| reward_log(event, index as u64))
+        .collect();
+    let token_event = TokenRewardsAdded {
+        token: Address::from([0x42; 20]),
+        amount: rewards[0].amount.into(),
+        totalRewards: rewards[0].total_reward.into(),
+        timestamp: rewards[0].timestamp.into(),
+        previousRewardChain: FixedBytes(rewards[0].previous_event_hash),
+        currentRewardChain: FixedBytes([0x11; 32]),
+    };
+    logs.push(raw_log(REWARD_CHAIN, &token_event, 1000));
+    logs.push(raw_log(STAKE_CHAIN, &token_event, 1001));
+
+    let decoded = reward_events(&logs, &REWARD_CHAIN).unwrap();
+    assert_eq!(decoded.len(), rewards.len() + 1);
+    for (logged, event) in decoded.iter().zip(&rewards) {
+        assert_eq!(logged.event.encode_packed(), event.encode_packed());
+        assert_eq!(logged.event.current_event_hash, event.current_event_hash);
+    }
+    let token = &decoded.last().unwrap().event;
+    assert_eq!(token.token, Some([0x42; 20]));
+    assert_eq!(token.current_event_hash, [0x11; 32]);
+}
+
+#[test]
+fn parses_eth_get_logs_output() {
+    let event = &load_stakes()[0];
+    let log = stake_log(event, 0);
+    let json = serde_json::json!({
+        "address": "0x5fbdb2315678afecb367f032d93f642f64180aa3",
+        "topics": log.topics.iter().map(|topic| format!("0x{}", hex::encode(topic))).collect::<Vec<_>>(),
+        "data": format!("0x{}", hex::encode(&log.data)),
+        "blockNumber": "0x1b4",
+        "transactionHash": format!("0x{}", hex::encode(log.transaction_hash)),
+        "transactionIndex": "0x0",
+        "blockHash": format!("0x{}", hex::encode([0x22; 32])),
+        "logIndex": "0x2",
+        "removed": false
+    });
+    let raw: RawLog = serde_json::from_value(json).unwrap();
+    assert_eq!(
+        hex::encode(raw.address),
+        "5fbdb2315678afecb367f032d93f642f64180aa3"
+    );
+    assert_eq!(raw.block_number, 0x1b4);
+    assert_eq!(raw.log_index, 2);
+    assert_eq!(raw.topics, log.topics);
+
+    let mut logged = raw.stake_event().unwrap();
+    assert!(logged.event.verify_hash());
+    assert_eq!(logged.event.user, event.user);
+
+    // The transaction hash reads back from hex like the hashes of the event.
+    let mut json = serde_json::to_value(&logged).unwrap();
+    json["transactionHash"] = format!("0x{}", hex::encode(log.transaction_hash)).into();
+    json["currentStakeChain"] = format!("0x{}", hex::encode(event.current_event_hash)).into();
+    let parsed: LoggedEvent<StakeChainExtendedEvent> = serde_json::from_value(json).unwrap();
+    assert_eq!(parsed.transaction_hash, log.transaction_hash);
+    assert_eq!(parsed.event.current_event_hash, event.current_event_hash);
+}
+
+#[test]
+fn rejects_other_and_malformed_logs() {
+    let stake = stake_log(&load_stakes()[0], 7);
+    let reward = reward_log(&load_rewards()[0], 8);
+
+    match reward.stake_event().unwrap_err() {
+        SoarError::InvalidLog { log_index: 2, .. } => {}
+        error => panic!("unexpected error {:?}", error),
+    }
+    match stake.reward_event().unwrap_err() {
+        SoarError::InvalidLog { log_index: 1, .. } => {}
+        error => panic!("unexpected error {:?}", error),
+    }
+
+    let mut truncated = stake.clone();
+    truncated.data.truncate(64);
+    match stake_events(&[truncated], &STAKE_CHAIN).unwrap_err() {
+        SoarError::InvalidLog { log_index: 1, .. } => {}
+        error => panic!("unexpected error {:?}", error),
+    }
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use common::load_input;
+use soar_lib::error::{Chain, Endpoint, SoarError};
+use soar_lib::merkle::{leaf_hash, verify_proof, MerkleTree, RewardTree};
+use soar_lib::types::{Bytes32, Zero, U256};
+
+fn leaves(count: u8) -> Vec<Bytes32> {
+    (0..count)
+        .map(|index| leaf_hash(&[index; 20], U256::from(index)))
+        .collect()
+}
+
+#[test]
+fn proves_every_leaf() {
+    for count in 1..=9 {
+        let leaves = leaves(count);
+        let tree = MerkleTree::new(leaves.clone());
+        for (index, leaf) in leaves.iter().enumerate() {
+            let proof = tree.proof(index).unwrap();
+            assert!(verify_proof(*leaf, &proof, tree.root()));
+            assert!(!verify_proof(
+                leaf_hash(&[0xff; 20], U256::one()),
+                &proof,
+                tree.root()
+            ));
+        }
+        assert_eq!(tree.proof(leaves.len()), None);
+    }
+}
+
+#[test]
+fn empty_tree_has_zero_root() {
+    assert_eq!(MerkleTree::new(Vec::new()).root(), Bytes32::zero());
+    assert_eq!(MerkleTree::new(leaves(1)).root(), leaves(1)[0]);
+}
+
+#[test]
+fn leaf_packs_user_and_reward() {
+    let user = [0x11; 20];
+    let mut packed = user.to_vec();
+    packed.extend_from_slice(&U256::from(7).to_big_endian());
+    assert_eq!(
+        leaf_hash(&user, U256::from(7)),
+        soar_lib::types::keccak256(&packed)
+    );
+}
+
+#[test]
+fn leaves_match_calculator() {
+    let mut calculator = load_input();
+    let tree = RewardTree::from_calculator(&mut calculator).unwrap();
+    let proof = tree.proof(&calculator.user).unwrap();
+    assert_eq!(proof.reward, calculator.calculate_reward().unwrap());
+    for (user, _) in tree.rewards() {
+        assert!(tree.proof(user).unwrap().verify(tree.root()));
+    }
+
+    // The user of stake event 10 stakes for the first time in the window.
+    calculator.user = calculator.stake_events[10].user;
+    calculator.claim.user = calculator.user;
+    calculator.claim.from_user_stake_event = None;
+    calculator.claim.to_user_stake_event = Some(calculator.stake_events[13].clone());
+    assert_eq!(
+        tree.proof(&calculator.user).unwrap().reward,
+        calculator.calculate_reward().unwrap()
+    );
+}
+
+#[test]
+fn leaves_match_public_values_of_every_staker() {
+    let mut calculator = load_input();
+    let tree = RewardTree::from_calculator(&mut calculator).unwrap();
+    for (user, reward) in tree.rewards() {
+        let mut claim = calculator.clone();
+        claim.user = *user;
+        claim.claim.user = *user;
+        claim.claim.from_user_stake_event = calculator
+            .stake_history
+            .iter()
+            .rev()
+            .find(|event| event.user == *user)
+            .cloned();
+        claim.claim.to_user_stake_event = calculator
+            .stake_events
+            .iter()
+            .rev()
+            .find(|event| event.user == *user)
+            .cloned()
+            .or_else(|| claim.claim.from_user_stake_event.clone());
+        let public_values = claim.public_values().unwrap();
+        assert_eq!(public_values.user.0 .0, *user);
+        assert_eq!(
+            public_values.total_rewards.to_be_bytes::<32>(),
+            reward.to_big_endian()
+        );
+    }
+}
+
+#[test]
+fn total_reward_does_not_exceed_rewards() {
+    let mut calculator = load_input();
+    let tree = RewardTree::from_calculator(&mut calculator).unwrap();
+    let rewarded = calculator
+        .reward_events
+        .iter()
+        .fold(U256::zero(), |total, event| total + event.amount);
+    assert!(tree.total_reward().unwrap() <= rewarded);
+}
+
+#[test]
+fn rejects_truncated_stake_history() {
+    let mut calculator = load_input();
+    calculator.stake_history.remove(0);
+    assert!(matches!(
+        RewardTree::from_calculator(&mut calculator),
+        Err(SoarError::SnapshotTotalMismatch { .. })
+    ));
+}
+
+#[test]
+fn rejects_stake_history_with_a_gap() {
+    let mut calculator = load_input();
+    calculator.stake_history.remove(1);
+    assert!(matches!(
+        RewardTree::from_calculator(&mut calculator),
+        Err(SoarError::ChainLink {
+            chain: Chain::StakeHistory,
+            index: 1,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn rejects_stake_history_not_reaching_the_segment() {
+    let mut calculator = load_input();
+    calculator.stake_history.pop();
+    assert!(matches!(
+        RewardTree::from_calculator(&mut calculator),
+        Err(SoarError::EndpointMismatch {
+            chain: Chain::StakeHistory,
+            endpoint: Endpoint::To,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,84 @@
+use num_bigint::BigUint;
+use proptest::prelude::*;
+use soar_lib::error::SoarError;
+use soar_lib::types::{mul_div, mul_div_scaled, U256, U512};
+
+fn to_big(value: U256) -> BigUint {
+    BigUint::from_bytes_be(&value.to_big_endian())
+}
+
+fn to_big_512(value: U512) -> BigUint {
+    BigUint::from_bytes_be(&value.to_big_endian())
+}
+
+fn u256() -> impl Strategy<Value = U256> {
+    any::<[u64; 4]>().prop_map(U256)
+}
+
+/// Values that are realistic for 18-decimal tokens, up to ~2^128.
+fn token_amount() -> impl Strategy<Value = U256> {
+    any::<u128>().prop_map(U256::from)
+}
+
+proptest! {
+    #[test]
+    fn mul_div_matches_reference(a in u256(), b in u256(), denominator in u256()) {
+        prop_assume!(!denominator.is_zero());
+        let expected = to_big(a) * to_big(b) / to_big(denominator);
+        match mul_div(a, b, denominator) {
+            Ok(result) => prop_assert_eq!(to_big(result), expected),
+            Err(error) => {
+                prop_assert_eq!(error, SoarError::Overflow);
+                prop_assert!(expected.bits() > 256);
+            }
+        }
+    }
+
+    #[test]
+    fn mul_div_is_exact_for_shares(total in u256(), share in u256(), amount in u256()) {
+        // A user's stake never exceeds the total stake, so the share always fits.
+        prop_assume!(!total.is_zero());
+        let user = if share <= total { share } else { share % total };
+        let expected = to_big(user) * to_big(amount) / to_big(total);
+        prop_assert_eq!(to_big(mul_div(user, amount, total).unwrap()), expected);
+    }
+
+    #[test]
+    fn mul_div_scaled_matches_reference(
+        user in token_amount(),
+        extra in token_amount(),
+        amount in u256(),
+    ) {
+        let total = user.checked_add(extra).unwrap();
+        prop_assume!(!total.is_zero());
+        let precision = U256::from(1_000_000_000_000_000_000u64);
+        let expected = to_big(user) * to_big(amount) * to_big(precision) / to_big(total);
+        let result = mul_div_scaled(user, amount, precision, total).unwrap();
+        prop_assert_eq!(to_big_512(result), expected);
+    }
+}
+
+#[test]
+fn mul_div_handles_products_beyond_256_bits() {
+    let max = U256::MAX;
+    assert_eq!(mul_div(max, max, max), Ok(max));
+    let precision = U256::from(1_000_000_000_000_000_000u64);
+    let scaled = mul_div_scaled(max, max, precision, max).unwrap();
+    assert_eq!(to_big_512(scaled), to_big(max) * to_big(precision));
+}
+
+#[test]
+fn mul_div_rejects_zero_denominator() {
+    assert_eq!(
+        mul_div(U256::one(), U256::one(), U256::zero()),
+        Err(SoarError::DivisionByZero)
+    );
+}
+
+#[test]
+fn mul_div_reports_overflow() {
+    assert_eq!(
+        mul_div(U256::MAX, U256::MAX, U256::one()),
+        Err(SoarError::Overflow)
+    );
+}
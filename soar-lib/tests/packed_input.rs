@@ -0,0 +1,110 @@
+mod common;
+
+use common::{load_input, INPUT};
+use soar_lib::error::{Chain, SoarError};
+use soar_lib::packed::{decode_input, encode_input, PACKED_INPUT_MAGIC};
+use soar_lib::reward_calculator::RewardCalculator;
+use soar_lib::stake::STAKE_EVENT_PACKED_LEN;
+use soar_lib::types::U256;
+
+fn to_value(calculator: &RewardCalculator) -> serde_json::Value {
+    serde_json::to_value(calculator).unwrap()
+}
+
+#[test]
+fn round_trips_json_input() {
+    let mut calculator = load_input();
+    let packed = encode_input(&calculator);
+    assert!(packed.starts_with(&PACKED_INPUT_MAGIC));
+    assert!(packed.len() * 2 < INPUT.len());
+
+    let mut decoded = RewardCalculator::try_from(packed).unwrap();
+    assert_eq!(to_value(&decoded), to_value(&calculator));
+    assert_eq!(decoded.claim_digest(), calculator.claim_digest());
+    assert_eq!(
+        decoded.calculate_reward().unwrap(),
+        calculator.calculate_reward().unwrap()
+    );
+}
+
+#[test]
+fn round_trips_token_events() {
+    let mut calculator = load_input();
+    let mut previous_hash = calculator.reward_events[0].previous_event_hash;
+    let mut token_total = U256::zero();
+    for event in calculator.reward_events.iter_mut().skip(1).step_by(2) {
+        token_total += event.amount;
+        event.token = Some([0x42; 20]);
+        event.total_reward = token_total;
+    }
+    for event in calculator.reward_events.iter_mut() {
+        event.previous_event_hash = previous_hash;
+        previous_hash = event.hash();
+        event.current_event_hash = previous_hash;
+    }
+    calculator.claim.to_reward_event = calculator.reward_events.last().unwrap().clone();
+
+    let mut decoded = decode_input(&encode_input(&calculator)).unwrap();
+    assert_eq!(to_value(&decoded), to_value(&calculator));
+    assert_eq!(
+        decoded.calculate_token_rewards().unwrap(),
+        calculator.calculate_token_rewards().unwrap()
+    );
+}
+
+#[test]
+fn decoded_events_link_by_recomputed_hashes() {
+    let calculator = load_input();
+    let mut packed = encode_input(&calculator);
+    let mut decoded = decode_input(&packed).unwrap();
+    decoded.verify().unwrap();
+
+    let first_stake = packed
+        .windows(STAKE_EVENT_PACKED_LEN)
+        .position(|window| window == calculator.stake_events[0].encode_packed())
+        .unwrap();
+    // Flip a bit of the amount of the first stake event of the segment.
+    packed[first_stake + 52] ^= 1;
+    let mut tampered = decode_input(&packed).unwrap();
+    match tampered.verify().unwrap_err() {
+        SoarError::ChainLink {
+            chain: Chain::Stake,
+            index: 1,
+            ..
+        } => {}
+        error => panic!("unexpected error {:?}", error),
+    }
+}
+
+#[test]
+fn rejects_malformed_input() {
+    let packed = encode_input(&load_input());
+    let offset = |result: Result<RewardCalculator, SoarError>| match result {
+        Err(SoarError::PackedInput { offset }) => offset,
+        result => panic!("unexpected result {:?}", result.map(|_| ())),
+    };
+
+    // Truncated in the last event of the stake history.
+    assert_eq!(
+        offset(decode_input(&packed[..packed.len() - 1])),
+        packed.len() - STAKE_EVENT_PACKED_LEN
+    );
+
+    let mut trailing = packed.clone();
+    trailing.push(0);
+    assert_eq!(offset(decode_input(&trailing)), packed.len());
+
+    let mut version = packed.clone();
+    version[PACKED_INPUT_MAGIC.len()] = 1;
+    assert_eq!(offset(decode_input(&version)), PACKED_INPUT_MAGIC.len());
+
+    // A nonzero token on an event flagged as the reward chain's own token.
+    let mut token = packed.clone();
+    let from_reward = PACKED_INPUT_MAGIC.len() + 1 + 2 * 20 + 1;
+    token[from_reward + 1] = 1;
+    assert_eq!(offset(decode_input(&token)), from_reward);
+
+    let mut flag = packed;
+    flag[from_reward - 1] = 2;
+    assert_eq!(offset(decode_input(&flag)), from_reward - 1);
+}
@@ -0,0 +1,337 @@
+//! Invariants of `RewardCalculator` over random chains from the dataset generator, and the
+//! errors it reports for chains tampered with one event at a time.
+
+use proptest::prelude::*;
+use soar_lib::breakdown::RewardBreakdown;
+use soar_lib::dataset::{generate, user_address, Dataset, DatasetParams};
+use soar_lib::engine::{AccumulatorEngine, ReplayEngine};
+use soar_lib::error::{Chain, Endpoint, SoarError};
+use soar_lib::input::{EventSelector, InputBuilder, SegmentSelection};
+use soar_lib::ledger::RewardLedger;
+use soar_lib::reward_calculator::RewardCalculator;
+use soar_lib::rounding::{RoundingPolicy, PRECISION};
+use soar_lib::types::{keccak256, U256, U512};
+
+fn dataset_params() -> impl Strategy<Value = DatasetParams> {
+    (any::<u64>(), 2..8usize, 10..60usize).prop_map(|(seed, users, iterations)| DatasetParams {
+        seed,
+        users,
+        iterations,
+        ..DatasetParams::default()
+    })
+}
+
+/// A generated dataset, skipping the seeds that give no claim.
+fn dataset(params: &DatasetParams) -> Result<Dataset, TestCaseError> {
+    match generate(params) {
+        Ok(dataset) => Ok(dataset),
+        Err(SoarError::InvalidDatasetParams(_)) => Err(TestCaseError::reject("no claim")),
+        Err(error) => Err(TestCaseError::fail(error.to_string())),
+    }
+}
+
+/// The claim's input as the prover receives it, without cached hashes.
+fn claim_input(dataset: &Dataset) -> RewardCalculator {
+    RewardCalculator::try_from(dataset.input_json()).unwrap()
+}
+
+/// The segments of the dataset's claim.
+fn claim_selection(calculator: &mut RewardCalculator) -> SegmentSelection {
+    let claim = &mut calculator.claim;
+    SegmentSelection {
+        from_stake: claim
+            .from_stake_event
+            .as_mut()
+            .map(|event| EventSelector::Hash(event.hash())),
+        to_stake: EventSelector::Hash(claim.to_stake_event.hash()),
+        from_reward: claim
+            .from_reward_event
+            .as_mut()
+            .map(|event| EventSelector::Hash(event.hash())),
+        to_reward: EventSelector::Hash(claim.to_reward_event.hash()),
+    }
+}
+
+fn builder(dataset: &Dataset) -> InputBuilder {
+    InputBuilder::new(
+        dataset.stakes.events.clone(),
+        dataset.rewards.events.clone(),
+    )
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn rewards_of_all_users_do_not_exceed_the_rewards(params in dataset_params()) {
+        let dataset = dataset(&params)?;
+        let mut claim = dataset.calculator.clone();
+        let builder = builder(&dataset);
+
+        let mut total_reward = U256::zero();
+        let mut event_shares = vec![U512::zero(); claim.reward_events.len()];
+        for user in (0..params.users).map(user_address) {
+            let mut calculator = builder.build(user, claim_selection(&mut claim)).unwrap();
+            total_reward += calculator.calculate_reward().unwrap();
+            let breakdown = RewardBreakdown::from_calculator(&mut calculator).unwrap();
+            for (total, event) in event_shares.iter_mut().zip(&breakdown.events) {
+                *total += event.share;
+            }
+        }
+
+        let mut rewards = U256::zero();
+        for (event, shares) in claim.reward_events.iter().zip(event_shares) {
+            prop_assert!(shares <= U512::from(event.amount) * U512::from(PRECISION));
+            rewards += event.amount;
+        }
+        prop_assert!(total_reward <= rewards);
+    }
+
+    #[test]
+    fn engines_agree_within_one_unit_for_every_user(params in dataset_params()) {
+        let dataset = dataset(&params)?;
+        let mut claim = dataset.calculator.clone();
+        let builder = builder(&dataset);
+        let floor_at_end = ReplayEngine { policy: RoundingPolicy::FloorAtEnd };
+        for user in (0..params.users).map(user_address) {
+            let mut calculator = builder.build(user, claim_selection(&mut claim)).unwrap();
+            let replay = calculator.calculate_reward_with(&floor_at_end).unwrap();
+            let accumulator = calculator.calculate_reward_with(&AccumulatorEngine).unwrap();
+            prop_assert!(replay.max(accumulator) - replay.min(accumulator) <= U256::one());
+        }
+    }
+
+    #[test]
+    fn rewards_are_additive_across_split_windows(
+        params in dataset_params(),
+        split in any::<prop::sample::Index>(),
+    ) {
+        let dataset = dataset(&params)?;
+        let mut claim = dataset.calculator.clone();
+        prop_assume!(claim.reward_events.len() > 1);
+        let builder = builder(&dataset);
+        let selection = claim_selection(&mut claim);
+
+        // Both chains end the first window and start the second at a reward event.
+        let split_index = split.index(claim.reward_events.len() - 1);
+        let split = &mut claim.reward_events[split_index];
+        let split_timestamp = EventSelector::Timestamp(split.timestamp);
+        prop_assume!(dataset.stakes.events[0].timestamp <= split.timestamp);
+        let first = SegmentSelection {
+            to_stake: split_timestamp,
+            to_reward: EventSelector::Hash(split.hash()),
+            ..selection
+        };
+        let second = SegmentSelection {
+            from_stake: Some(split_timestamp),
+            from_reward: Some(EventSelector::Hash(split.hash())),
+            ..selection
+        };
+
+        let mut whole = builder.build(claim.user, selection).unwrap();
+        let mut first = builder.build(claim.user, first).unwrap();
+        let mut second = builder.build(claim.user, second).unwrap();
+        let whole_breakdown = RewardBreakdown::from_calculator(&mut whole).unwrap();
+        let split_events: Vec<_> = RewardBreakdown::from_calculator(&mut first)
+            .unwrap()
+            .events
+            .into_iter()
+            .chain(RewardBreakdown::from_calculator(&mut second).unwrap().events)
+            .collect();
+        prop_assert_eq!(split_events.len(), whole_breakdown.events.len());
+        for (split, whole) in split_events.iter().zip(&whole_breakdown.events) {
+            prop_assert_eq!(split.reward_event.current_event_hash, whole.reward_event.current_event_hash);
+            prop_assert_eq!(split.user_stake, whole.user_stake);
+            prop_assert_eq!(split.total_stake, whole.total_stake);
+            prop_assert_eq!(split.share, whole.share);
+        }
+
+        // Each window floors its own reward, so the split may lose one unit.
+        let whole_reward = whole.calculate_reward().unwrap();
+        let split_reward = first.calculate_reward().unwrap() + second.calculate_reward().unwrap();
+        prop_assert!(split_reward <= whole_reward);
+        prop_assert!(whole_reward - split_reward <= U256::one());
+    }
+
+    #[test]
+    fn rejects_dropped_reward_event(params in dataset_params(), index in any::<prop::sample::Index>()) {
+        let dataset = dataset(&params)?;
+
+        let mut calculator = claim_input(&dataset);
+        prop_assume!(!calculator.reward_events.is_empty());
+        let index = index.index(calculator.reward_events.len());
+        calculator.reward_events.remove(index);
+        let error = calculator.calculate_reward().unwrap_err();
+        if index == calculator.reward_events.len() {
+            prop_assert!(matches!(
+                error,
+                SoarError::EndpointMismatch { chain: Chain::Reward, endpoint: Endpoint::To, .. }
+            ), "{:?}", error);
+        } else {
+            prop_assert!(matches!(
+                error,
+                SoarError::ChainLink { chain: Chain::Reward, index: link, .. } if link == index
+            ), "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn rejects_dropped_stake_event(params in dataset_params(), index in any::<prop::sample::Index>()) {
+        let dataset = dataset(&params)?;
+
+        let mut calculator = claim_input(&dataset);
+        prop_assume!(!calculator.stake_events.is_empty());
+        let index = index.index(calculator.stake_events.len());
+        calculator.stake_events.remove(index);
+        let error = calculator.calculate_reward().unwrap_err();
+        if index == calculator.stake_events.len() {
+            prop_assert!(matches!(
+                error,
+                SoarError::EndpointMismatch { chain: Chain::Stake, endpoint: Endpoint::To, .. }
+            ), "{:?}", error);
+        } else {
+            prop_assert!(matches!(
+                error,
+                SoarError::ChainLink { chain: Chain::Stake, index: link, .. } if link == index
+            ), "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn rejects_reordered_events(
+        params in dataset_params(),
+        first in any::<prop::sample::Index>(),
+        second in any::<prop::sample::Index>(),
+    ) {
+        let dataset = dataset(&params)?;
+
+        let mut calculator = claim_input(&dataset);
+        let len = calculator.reward_events.len();
+        let (first_reward, second_reward) = (first.index(len.max(1)), second.index(len.max(1)));
+        prop_assume!(first_reward != second_reward);
+        calculator.reward_events.swap(first_reward, second_reward);
+        let error = calculator.calculate_reward().unwrap_err();
+        prop_assert!(matches!(
+            error,
+            SoarError::ChainLink { chain: Chain::Reward, index, .. }
+                if index == first_reward.min(second_reward)
+        ), "{:?}", error);
+
+        let mut calculator = claim_input(&dataset);
+        let len = calculator.stake_events.len();
+        let (first_stake, second_stake) = (first.index(len.max(1)), second.index(len.max(1)));
+        prop_assume!(first_stake != second_stake);
+        calculator.stake_events.swap(first_stake, second_stake);
+        let error = calculator.calculate_reward().unwrap_err();
+        prop_assert!(matches!(
+            error,
+            SoarError::ChainLink { chain: Chain::Stake, index, .. }
+                if index == first_stake.min(second_stake)
+        ), "{:?}", error);
+    }
+
+    #[test]
+    fn rejects_duplicated_events(params in dataset_params(), index in any::<prop::sample::Index>()) {
+        let dataset = dataset(&params)?;
+
+        let mut calculator = claim_input(&dataset);
+        prop_assume!(!calculator.reward_events.is_empty());
+        let reward_index = index.index(calculator.reward_events.len());
+        let event = calculator.reward_events[reward_index].clone();
+        calculator.reward_events.insert(reward_index + 1, event);
+        let error = calculator.calculate_reward().unwrap_err();
+        prop_assert!(matches!(
+            error,
+            SoarError::ChainLink { chain: Chain::Reward, index, .. } if index == reward_index + 1
+        ), "{:?}", error);
+
+        let mut calculator = claim_input(&dataset);
+        prop_assume!(!calculator.stake_events.is_empty());
+        let stake_index = index.index(calculator.stake_events.len());
+        let event = calculator.stake_events[stake_index].clone();
+        calculator.stake_events.insert(stake_index + 1, event);
+        let error = calculator.calculate_reward().unwrap_err();
+        prop_assert!(matches!(
+            error,
+            SoarError::ChainLink { chain: Chain::Stake, index, .. } if index == stake_index + 1
+        ), "{:?}", error);
+    }
+
+    #[test]
+    fn rejects_altered_events(
+        params in dataset_params(),
+        index in any::<prop::sample::Index>(),
+        delta in 1..u64::MAX,
+        rehash in any::<bool>(),
+    ) {
+        let dataset = dataset(&params)?;
+
+        // Without updating its hash the event itself fails, with it the next link does.
+        let expect_error = |error: SoarError, chain: Chain, altered: usize, last: bool| {
+            if !rehash {
+                matches!(error, SoarError::HashMismatch { chain: c, index, .. } if c == chain && index == altered)
+            } else if last {
+                matches!(error, SoarError::EndpointMismatch { chain: c, endpoint: Endpoint::To, .. } if c == chain)
+            } else {
+                matches!(error, SoarError::ChainLink { chain: c, index, .. } if c == chain && index == altered + 1)
+            }
+        };
+
+        let mut calculator = claim_input(&dataset);
+        prop_assume!(!calculator.reward_events.is_empty());
+        let reward_index = index.index(calculator.reward_events.len());
+        let last = reward_index + 1 == calculator.reward_events.len();
+        let event = &mut calculator.reward_events[reward_index];
+        event.amount = event.amount.overflowing_add(U256::from(delta)).0;
+        if rehash {
+            event.current_event_hash = keccak256(&event.encode_packed());
+        }
+        let error = calculator.calculate_reward().unwrap_err();
+        prop_assert!(expect_error(error.clone(), Chain::Reward, reward_index, last), "{:?}", error);
+
+        let mut calculator = claim_input(&dataset);
+        prop_assume!(!calculator.stake_events.is_empty());
+        let stake_index = index.index(calculator.stake_events.len());
+        let last = stake_index + 1 == calculator.stake_events.len();
+        let event = &mut calculator.stake_events[stake_index];
+        event.total_staked = event.total_staked.overflowing_add(U256::from(delta)).0;
+        if rehash {
+            event.current_event_hash = keccak256(&event.encode_packed());
+        }
+        let error = calculator.calculate_reward().unwrap_err();
+        prop_assert!(expect_error(error.clone(), Chain::Stake, stake_index, last), "{:?}", error);
+    }
+
+    #[test]
+    fn rejects_tampered_stake_history(params in dataset_params(), index in any::<prop::sample::Index>()) {
+        let dataset = dataset(&params)?;
+        let calculator = claim_input(&dataset);
+        prop_assume!(!calculator.stake_history.is_empty());
+
+        // The first event is the latest of a staker holding stake at the start.
+        let mut truncated = calculator.clone();
+        let first = truncated.stake_history.remove(0);
+        prop_assert_eq!(
+            RewardLedger::from_stake_history(&mut truncated).unwrap_err(),
+            SoarError::SnapshotTotalMismatch {
+                expected: calculator.initial_total_stake(),
+                actual: calculator.initial_total_stake() - first.total_user_stake,
+            }
+        );
+
+        prop_assume!(calculator.stake_history.len() > 1);
+        let index = index.index(calculator.stake_history.len() - 1) + 1;
+        let mut dropped = calculator.clone();
+        dropped.stake_history.remove(index);
+        let error = RewardLedger::from_stake_history(&mut dropped).unwrap_err();
+        prop_assert!(
+            matches!(
+                error,
+                SoarError::ChainLink { chain: Chain::StakeHistory, .. }
+                    | SoarError::EndpointMismatch { chain: Chain::StakeHistory, .. }
+            ),
+            "{:?}",
+            error
+        );
+    }
+}
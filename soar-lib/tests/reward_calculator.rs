@@ -0,0 +1,236 @@
+mod common;
+
+use common::load_input;
+use soar_lib::error::{Chain, Endpoint, SoarError};
+use soar_lib::reward_calculator::RewardCalculator;
+use soar_lib::types::U256;
+
+#[test]
+fn calculates_reward_for_valid_input() {
+    let mut calculator = load_input();
+    calculator.calculate_reward().unwrap();
+}
+
+#[test]
+fn rejects_malformed_input() {
+    let error = RewardCalculator::try_from(b"{\"user\": 1}".to_vec()).unwrap_err();
+    assert!(matches!(error, SoarError::Json(_)));
+}
+
+#[test]
+fn rejects_dropped_reward_event() {
+    let mut calculator = load_input();
+    let expected = calculator.reward_events[2].current_event_hash;
+    let actual = calculator.reward_events[3].current_event_hash;
+    calculator.reward_events.remove(3);
+    assert_eq!(
+        calculator.calculate_reward(),
+        Err(SoarError::ChainLink {
+            chain: Chain::Reward,
+            index: 3,
+            expected,
+            actual,
+        })
+    );
+}
+
+#[test]
+fn rejects_reward_events_not_starting_at_from() {
+    let mut calculator = load_input();
+    calculator.reward_events.remove(0);
+    assert!(matches!(
+        calculator.calculate_reward(),
+        Err(SoarError::ChainLink {
+            chain: Chain::Reward,
+            index: 0,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn rejects_reward_events_not_ending_at_to() {
+    let mut calculator = load_input();
+    calculator.reward_events.pop();
+    assert!(matches!(
+        calculator.calculate_reward(),
+        Err(SoarError::EndpointMismatch {
+            chain: Chain::Reward,
+            endpoint: Endpoint::To,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn rejects_invented_reward_amount() {
+    let mut calculator = load_input();
+    let event = &mut calculator.reward_events[4];
+    event.amount *= 2;
+    assert!(matches!(
+        calculator.calculate_reward(),
+        Err(SoarError::HashMismatch {
+            chain: Chain::Reward,
+            index: 4,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn rejects_dropped_stake_event() {
+    let mut calculator = load_input();
+    calculator.stake_events.remove(2);
+    assert!(matches!(
+        calculator.calculate_reward(),
+        Err(SoarError::ChainLink {
+            chain: Chain::Stake,
+            index: 2,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn rejects_missing_trailing_stake_event() {
+    let mut calculator = load_input();
+    calculator.stake_events.pop();
+    assert!(matches!(
+        calculator.calculate_reward(),
+        Err(SoarError::EndpointMismatch {
+            chain: Chain::Stake,
+            endpoint: Endpoint::To,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn rejects_altered_trailing_stake_event() {
+    let mut calculator = load_input();
+    let last = calculator.stake_events.len() - 1;
+    calculator.stake_events[last].total_staked += U256::one();
+    assert!(matches!(
+        calculator.calculate_reward(),
+        Err(SoarError::HashMismatch {
+            chain: Chain::Stake,
+            index,
+            ..
+        }) if index == last
+    ));
+}
+
+#[test]
+fn rejects_stake_segment_starting_after_reward_events() {
+    let mut calculator = load_input();
+    // Stake event 1 is later than the first reward event.
+    calculator.claim.from_stake_event = Some(calculator.stake_events[1].clone());
+    calculator.stake_events.drain(..2);
+    assert_eq!(
+        calculator.calculate_reward(),
+        Err(SoarError::RewardBeforeStakeSegment { index: 0 })
+    );
+}
+
+#[test]
+fn verifies_user_without_events_in_window() {
+    let calculator = load_input();
+    assert!(!calculator
+        .stake_events
+        .iter()
+        .any(|event| event.user == calculator.user));
+    load_input().calculate_reward().unwrap();
+}
+
+#[test]
+fn verifies_user_with_events_in_window() {
+    let mut calculator = load_input();
+    // The user of stake event 10 stakes for the first time and unstakes in stake event 13.
+    calculator.user = calculator.stake_events[10].user;
+    calculator.claim.user = calculator.user;
+    calculator.claim.from_user_stake_event = None;
+    calculator.claim.to_user_stake_event = Some(calculator.stake_events[13].clone());
+    calculator.calculate_reward().unwrap();
+}
+
+#[test]
+fn rejects_to_user_stake_event_before_last_user_event() {
+    let mut calculator = load_input();
+    calculator.user = calculator.stake_events[10].user;
+    calculator.claim.user = calculator.user;
+    calculator.claim.from_user_stake_event = None;
+    calculator.claim.to_user_stake_event = Some(calculator.stake_events[10].clone());
+    assert!(matches!(
+        calculator.calculate_reward(),
+        Err(SoarError::EndpointMismatch {
+            chain: Chain::UserStake,
+            endpoint: Endpoint::To,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn rejects_moved_to_user_stake_event() {
+    let mut calculator = load_input();
+    calculator.claim.to_user_stake_event = Some(calculator.stake_events[0].clone());
+    assert!(matches!(
+        calculator.calculate_reward(),
+        Err(SoarError::EndpointMismatch {
+            chain: Chain::UserStake,
+            endpoint: Endpoint::To,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn rejects_from_user_stake_event_of_other_user() {
+    let mut calculator = load_input();
+    calculator.claim.from_user_stake_event = calculator.claim.from_stake_event.clone();
+    assert!(matches!(
+        calculator.calculate_reward(),
+        Err(SoarError::UserMismatch { .. })
+    ));
+}
+
+#[test]
+fn rejects_from_user_stake_event_inside_segment() {
+    let mut calculator = load_input();
+    // The user of stake events 2 and 5 would skip their first stake in the window.
+    calculator.user = calculator.stake_events[2].user;
+    calculator.claim.user = calculator.user;
+    calculator.claim.from_user_stake_event = Some(calculator.stake_events[2].clone());
+    calculator.claim.to_user_stake_event = Some(calculator.stake_events[5].clone());
+    assert_eq!(
+        calculator.calculate_reward(),
+        Err(SoarError::UserSnapshotOutOfRange {
+            endpoint: Endpoint::From
+        })
+    );
+}
+
+#[test]
+fn public_values_commit_claim_endpoints() {
+    let mut calculator = load_input();
+    let reward = load_input().calculate_reward().unwrap();
+    let public_values = calculator.public_values().unwrap();
+    assert_eq!(public_values.user.0 .0, calculator.user);
+    assert_eq!(
+        public_values.total_rewards.to_be_bytes::<32>(),
+        reward.to_big_endian()
+    );
+    assert_eq!(
+        public_values.to_reward_event_hash.0,
+        calculator.claim.to_reward_event.hash()
+    );
+    assert_eq!(
+        public_values.from_stake_event_hash.0,
+        calculator.claim.from_stake_event.as_mut().unwrap().hash()
+    );
+    assert_eq!(public_values.token_rewards.len(), 1);
+    assert_eq!(
+        public_values.token_rewards[0].amount.to_be_bytes::<32>(),
+        reward.to_big_endian()
+    );
+}
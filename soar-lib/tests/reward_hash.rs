@@ -0,0 +1,112 @@
+use soar_lib::error::SoarError;
+use soar_lib::reward::{RewardChainExtendedEvent, RewardChainExtendedEventJSON};
+use soar_lib::types::{Bytes32, Zero, U256};
+
+const REWARDS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/../data/rewards.json"));
+
+fn load_rewards() -> Vec<RewardChainExtendedEvent> {
+    let events: Vec<RewardChainExtendedEventJSON> = serde_json::from_str(REWARDS).unwrap();
+    events
+        .into_iter()
+        .map(RewardChainExtendedEvent::try_from)
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn first_reward_event_matches_solidity() {
+    let mut event = load_rewards().remove(0);
+    assert_eq!(event.previous_event_hash, Bytes32::zero());
+    assert_eq!(
+        hex::encode(event.hash()),
+        "cf108076e914be2adcd52e777de2aec788150f8f9cbdfcd45cddfc3519d2059e"
+    );
+}
+
+#[test]
+fn packed_encoding_layout() {
+    let event = load_rewards().remove(0);
+    let packed = event.encode_packed();
+    assert_eq!(packed.len(), soar_lib::reward::REWARD_EVENT_PACKED_LEN);
+    assert_eq!(&packed[0..32], &event.amount.to_big_endian());
+    assert_eq!(&packed[96..128], &event.previous_event_hash);
+}
+
+#[test]
+fn every_reward_event_verifies() {
+    for (index, mut event) in load_rewards().into_iter().enumerate() {
+        assert!(event.verify_hash(), "reward event {} hash mismatch", index);
+    }
+}
+
+#[test]
+fn every_reward_event_links_to_previous() {
+    let mut events = load_rewards();
+    for index in 1..events.len() {
+        let previous = events[index - 1].hash();
+        assert_eq!(previous, events[index].previous_event_hash);
+    }
+}
+
+#[test]
+fn tampered_reward_event_fails() {
+    let mut event = load_rewards().remove(2);
+    event.amount += U256::one();
+    assert!(!event.verify_hash());
+}
+
+#[test]
+fn forged_current_hash_fails() {
+    let mut event = load_rewards().remove(2);
+    event.current_event_hash[0] ^= 1;
+    assert!(!event.verify_hash());
+}
+
+#[test]
+fn rejects_malformed_amount() {
+    let mut events: Vec<RewardChainExtendedEventJSON> = serde_json::from_str(REWARDS).unwrap();
+    let mut event = events.remove(0);
+    event.amount = "12e18".to_string();
+    assert!(matches!(
+        RewardChainExtendedEvent::try_from(event),
+        Err(SoarError::Parse {
+            field: "amount",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn token_packed_encoding_layout() {
+    let mut event = load_rewards().remove(0);
+    let untokened = event.encode_packed();
+    event.token = Some([0x42; 20]);
+    let packed = event.encode_packed();
+    assert_eq!(
+        packed.len(),
+        soar_lib::reward::TOKEN_REWARD_EVENT_PACKED_LEN
+    );
+    assert_eq!(&packed[0..20], &[0x42; 20]);
+    assert_eq!(&packed[20..], &untokened[..]);
+    assert!(!event.verify_hash());
+}
+
+#[test]
+fn rejects_zero_address_token() {
+    let mut event = load_rewards().remove(0);
+    event.token = Some([0; 20]);
+    event.current_event_hash = event.hash();
+    assert!(!event.verify_hash());
+    assert!(RewardChainExtendedEvent::decode_packed(&event.encode_packed()).is_none());
+
+    let mut json = RewardChainExtendedEventJSON::from(&event);
+    assert_eq!(
+        RewardChainExtendedEvent::try_from(json.clone()).unwrap_err(),
+        SoarError::Parse {
+            field: "token",
+            value: format!("0x{}", hex::encode([0; 20])),
+        }
+    );
+    json.token = Some(format!("0x{}", hex::encode([0x42; 20])));
+    assert!(RewardChainExtendedEvent::try_from(json).is_ok());
+}
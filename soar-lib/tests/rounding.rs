@@ -0,0 +1,137 @@
+mod common;
+
+use common::load_input;
+use soar_lib::ledger::RewardLedger;
+use soar_lib::rounding::{RewardDistribution, RoundingPolicy, PRECISION};
+use soar_lib::types::{U256, U512};
+
+fn distribution(policy: RoundingPolicy) -> RewardDistribution {
+    let mut calculator = load_input();
+    calculator.calculate_distribution(policy).unwrap()
+}
+
+fn total_share(distribution: &RewardDistribution) -> U512 {
+    distribution
+        .events
+        .iter()
+        .fold(U512::zero(), |total, event| total + event.share)
+}
+
+/// Every sub-unit of the user's shares is either paid out or reported as dust.
+fn assert_reconciles(distribution: &RewardDistribution) {
+    let paid = U512::from(distribution.total_reward) * U512::from(PRECISION);
+    assert_eq!(paid + distribution.remainder, total_share(distribution));
+}
+
+#[test]
+fn default_policy_matches_calculate_reward() {
+    let mut calculator = load_input();
+    let total_reward = calculator.calculate_reward().unwrap();
+    assert_eq!(
+        distribution(RoundingPolicy::default()).total_reward,
+        total_reward
+    );
+}
+
+#[test]
+fn reports_one_entry_per_reward_event() {
+    let calculator = load_input();
+    let distribution = distribution(RoundingPolicy::FloorPerEvent);
+    assert_eq!(distribution.events.len(), calculator.reward_events.len());
+    for (rounding, event) in distribution.events.iter().zip(&calculator.reward_events) {
+        assert_eq!(rounding.reward_event_hash, event.current_event_hash);
+    }
+}
+
+#[test]
+fn floor_per_event_loses_each_fraction() {
+    let distribution = distribution(RoundingPolicy::FloorPerEvent);
+    assert_reconciles(&distribution);
+    let precision = U512::from(PRECISION);
+    let mut total_paid = U256::zero();
+    for event in &distribution.events {
+        assert_eq!(U512::from(event.paid), event.share / precision);
+        assert_eq!(event.remainder, event.share % precision);
+        total_paid += event.paid;
+    }
+    assert_eq!(total_paid, distribution.total_reward);
+}
+
+#[test]
+fn floor_at_end_pays_once() {
+    let distribution = distribution(RoundingPolicy::FloorAtEnd);
+    assert_reconciles(&distribution);
+    assert!(distribution.remainder < U512::from(PRECISION));
+    for event in &distribution.events {
+        assert!(event.paid.is_zero());
+        assert_eq!(event.remainder, event.share % U512::from(PRECISION));
+    }
+}
+
+#[test]
+fn carry_remainder_attributes_floor_at_end_total() {
+    let carried = distribution(RoundingPolicy::CarryRemainder);
+    let at_end = distribution(RoundingPolicy::FloorAtEnd);
+    assert_reconciles(&carried);
+    assert_eq!(carried.total_reward, at_end.total_reward);
+    assert_eq!(carried.remainder, at_end.remainder);
+    let total_paid = carried
+        .events
+        .iter()
+        .fold(U256::zero(), |total, event| total + event.paid);
+    assert_eq!(total_paid, carried.total_reward);
+    assert!(carried
+        .events
+        .iter()
+        .all(|event| event.remainder < U512::from(PRECISION)));
+}
+
+#[test]
+fn floor_per_event_never_pays_more() {
+    let per_event = distribution(RoundingPolicy::FloorPerEvent);
+    let at_end = distribution(RoundingPolicy::FloorAtEnd);
+    assert!(per_event.total_reward <= at_end.total_reward);
+    assert!(per_event.remainder >= at_end.remainder);
+}
+
+#[test]
+fn treasury_dust_is_what_no_staker_is_paid() {
+    let mut calculator = load_input();
+    let ledger = RewardLedger::from_stake_history(&mut calculator).unwrap();
+    let dust = ledger.dust();
+    assert_eq!(dust.len(), calculator.reward_events.len());
+    let stakers = U256::from(ledger.entries().len());
+    for (dust, event) in dust.iter().zip(&calculator.reward_events) {
+        assert_eq!(dust.reward_event_hash, event.current_event_hash);
+        assert_eq!(dust.paid + dust.dust, event.amount);
+        // Each staker's share loses less than one unit.
+        assert!(dust.dust < stakers);
+    }
+    let rewarded = calculator
+        .reward_events
+        .iter()
+        .fold(U256::zero(), |total, event| total + event.amount);
+    let paid = ledger
+        .entries()
+        .values()
+        .fold(U256::zero(), |total, entry| total + entry.reward);
+    assert_eq!(ledger.total_dust().unwrap(), rewarded - paid);
+}
+
+#[test]
+fn treasury_dust_is_exact() {
+    let mut reward_event = load_input().reward_events[0].clone();
+    reward_event.amount = U256::from(2);
+    let mut ledger = RewardLedger::new(U256::from(3))
+        .with_user_stake([1; 20], U256::one())
+        .with_user_stake([2; 20], U256::one())
+        .with_user_stake([3; 20], U256::one());
+    ledger.apply(&[], &[reward_event.clone()]).unwrap();
+    // Every staker's two thirds of a unit are floored away, none of the amount is paid.
+    assert_eq!(ledger.dust()[0].paid, U256::zero());
+    assert_eq!(ledger.dust()[0].dust, U256::from(2));
+
+    let mut empty = RewardLedger::new(U256::zero());
+    empty.apply(&[], &[reward_event]).unwrap();
+    assert_eq!(empty.total_dust(), Ok(U256::from(2)));
+}
@@ -0,0 +1,143 @@
+mod common;
+
+use common::{load_input, INPUT};
+use soar_lib::reward::RewardChainExtendedEvent;
+use soar_lib::reward_calculator::RewardCalculator;
+use soar_lib::stake::StakeChainExtendedEvent;
+use soar_lib::types::U256;
+
+const INPUT_PLAIN: &[u8] = include_bytes!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../data/input-plain.json"
+));
+const STAKES: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/../data/stakes.json"));
+const REWARDS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/../data/rewards.json"));
+
+#[test]
+fn plain_input_matches_limb_input() {
+    let mut limbs = load_input();
+    let mut plain = RewardCalculator::try_from(INPUT_PLAIN.to_vec()).unwrap();
+    assert_eq!(plain.user, limbs.user);
+    assert_eq!(plain.claim_digest(), limbs.claim_digest());
+    assert_eq!(
+        plain.calculate_reward().unwrap(),
+        limbs.calculate_reward().unwrap()
+    );
+}
+
+#[test]
+fn event_dumps_parse_directly() {
+    let mut stakes: Vec<StakeChainExtendedEvent> = serde_json::from_str(STAKES).unwrap();
+    let mut rewards: Vec<RewardChainExtendedEvent> = serde_json::from_str(REWARDS).unwrap();
+    assert!(stakes.iter_mut().all(|event| event.verify_hash()));
+    assert!(rewards.iter_mut().all(|event| event.verify_hash()));
+}
+
+#[test]
+fn u256_accepts_decimal_hex_and_limbs() {
+    let expected = U256::from(1_000_000_000_000_000_000u64) * U256::from(45u64);
+    for json in [
+        r#""45000000000000000000""#,
+        r#""0x270801d946c940000""#,
+        "[8106511852580896768, 2, 0, 0]",
+    ] {
+        assert_eq!(
+            serde_json::from_str::<U256>(json).unwrap(),
+            expected,
+            "{}",
+            json
+        );
+    }
+}
+
+#[test]
+fn rejects_malformed_values() {
+    for json in [
+        r#""12ab""#,
+        r#""0xzz""#,
+        "[1, 2, 3]",
+        "[1, 2, 3, 4, 5]",
+        "-1",
+    ] {
+        assert!(serde_json::from_str::<U256>(json).is_err(), "{}", json);
+    }
+
+    let mut event: serde_json::Value = serde_json::from_str::<Vec<serde_json::Value>>(STAKES)
+        .unwrap()
+        .remove(0);
+    event["user"] = "0x1234".into();
+    assert!(serde_json::from_value::<StakeChainExtendedEvent>(event.clone()).is_err());
+    event["user"] = serde_json::json!([1, 2, 3]);
+    assert!(serde_json::from_value::<StakeChainExtendedEvent>(event).is_err());
+}
+
+fn to_value(calculator: &RewardCalculator) -> serde_json::Value {
+    serde_json::to_value(calculator).unwrap()
+}
+
+#[test]
+fn limb_input_round_trips() {
+    let calculator = load_input();
+    let serialized = serde_json::to_vec(&calculator).unwrap();
+    let original: serde_json::Value = serde_json::from_slice(INPUT).unwrap();
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(&serialized).unwrap(),
+        original
+    );
+
+    let round_trip = RewardCalculator::try_from(serialized).unwrap();
+    assert_eq!(to_value(&round_trip), to_value(&calculator));
+}
+
+#[test]
+fn plain_input_round_trips() {
+    let mut plain = RewardCalculator::try_from(INPUT_PLAIN.to_vec()).unwrap();
+    let mut round_trip = RewardCalculator::try_from(serde_json::to_vec(&plain).unwrap()).unwrap();
+    assert_eq!(to_value(&round_trip), to_value(&plain));
+    assert_eq!(to_value(&round_trip), to_value(&load_input()));
+    assert_eq!(round_trip.claim_digest(), plain.claim_digest());
+    assert_eq!(
+        round_trip.calculate_reward().unwrap(),
+        plain.calculate_reward().unwrap()
+    );
+}
+
+#[test]
+fn hash_cache_is_not_serialized() {
+    let mut stakes: Vec<StakeChainExtendedEvent> = serde_json::from_str(STAKES).unwrap();
+    let mut rewards: Vec<RewardChainExtendedEvent> = serde_json::from_str(REWARDS).unwrap();
+    let before = (
+        serde_json::to_value(&stakes[0]).unwrap(),
+        serde_json::to_value(&rewards[0]).unwrap(),
+    );
+    stakes[0].hash();
+    rewards[0].hash();
+    let after = (
+        serde_json::to_value(&stakes[0]).unwrap(),
+        serde_json::to_value(&rewards[0]).unwrap(),
+    );
+    assert_eq!(after, before);
+    assert!(after.0.get("hash").is_none());
+    assert!(after.1.get("hash").is_none());
+
+    let mut stake: StakeChainExtendedEvent = serde_json::from_value(after.0).unwrap();
+    let mut reward: RewardChainExtendedEvent = serde_json::from_value(after.1).unwrap();
+    assert!(stake.verify_hash());
+    assert!(reward.verify_hash());
+}
+
+#[test]
+fn token_round_trips() {
+    let mut reward: RewardChainExtendedEvent =
+        serde_json::from_str::<Vec<_>>(REWARDS).unwrap().remove(0);
+    assert!(serde_json::to_value(&reward)
+        .unwrap()
+        .get("token")
+        .is_none());
+
+    reward.token = Some([0x42; 20]);
+    let value = serde_json::to_value(&reward).unwrap();
+    assert_eq!(value["token"], serde_json::json!([0x42u8; 20].to_vec()));
+    let round_trip: RewardChainExtendedEvent = serde_json::from_value(value).unwrap();
+    assert_eq!(round_trip.token, Some([0x42; 20]));
+}
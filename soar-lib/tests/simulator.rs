@@ -0,0 +1,195 @@
+use soar_lib::error::SoarError;
+use soar_lib::input::{EventSelector, InputBuilder, SegmentSelection};
+use soar_lib::reward::RewardChainExtendedEvent;
+use soar_lib::simulator::{HashInterval, RewardChain, StakeChain};
+use soar_lib::stake::StakeChainExtendedEvent;
+use soar_lib::types::{Address, Bytes32, Zero, U256};
+
+const STAKES: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/../data/stakes.json"));
+const REWARDS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/../data/rewards.json"));
+
+const ALICE: Address = [0xa1; 20];
+const BOB: Address = [0xb0; 20];
+
+fn hashes<'a>(events: impl Iterator<Item = &'a Bytes32>) -> Vec<Bytes32> {
+    events.copied().collect()
+}
+
+#[test]
+fn replays_hardhat_stake_chain() {
+    let expected: Vec<StakeChainExtendedEvent> = serde_json::from_str(STAKES).unwrap();
+    let mut chain = StakeChain::new();
+    for event in &expected {
+        if event.is_stake {
+            chain
+                .stake(event.user, event.amount, event.timestamp)
+                .unwrap();
+        } else {
+            chain
+                .unstake(event.user, event.amount, event.timestamp)
+                .unwrap();
+        }
+    }
+    assert_eq!(
+        hashes(chain.events.iter().map(|event| &event.current_event_hash)),
+        hashes(expected.iter().map(|event| &event.current_event_hash))
+    );
+    let last = expected.last().unwrap();
+    assert_eq!(chain.total_staked, last.total_staked);
+    assert_eq!(chain.stake_chain, last.current_event_hash);
+    let (stake, snapshot, current) = chain.stake_snapshot(&last.user);
+    assert_eq!(stake, last.total_user_stake);
+    assert_eq!(snapshot, last.current_event_hash);
+    assert_eq!(current, last.current_event_hash);
+}
+
+#[test]
+fn replays_hardhat_reward_chain() {
+    let expected: Vec<RewardChainExtendedEvent> = serde_json::from_str(REWARDS).unwrap();
+    let mut chain = RewardChain::new();
+    for event in &expected {
+        chain.add_rewards(event.amount, event.timestamp).unwrap();
+    }
+    assert_eq!(
+        hashes(chain.events.iter().map(|event| &event.current_event_hash)),
+        hashes(expected.iter().map(|event| &event.current_event_hash))
+    );
+    assert_eq!(chain.total_rewards, expected.last().unwrap().total_reward);
+    assert_eq!(chain.reward_begin_time, expected[0].timestamp);
+}
+
+#[test]
+fn unstake_beyond_stake_reverts() {
+    let mut chain = StakeChain::new();
+    chain.stake(ALICE, U256::from(10), U256::from(100)).unwrap();
+    chain.stake(BOB, U256::from(5), U256::from(101)).unwrap();
+    assert_eq!(
+        chain.unstake(BOB, U256::from(6), U256::from(102)),
+        Err(SoarError::Reverted("arithmetic underflow"))
+    );
+    assert_eq!(chain.events.len(), 2);
+    assert_eq!(chain.total_staked, U256::from(15));
+
+    chain.unstake(BOB, U256::from(5), U256::from(102)).unwrap();
+    let event = chain.events.last().unwrap();
+    assert!(!event.is_stake);
+    assert_eq!(event.total_user_stake, U256::zero());
+    assert_eq!(event.total_staked, U256::from(10));
+}
+
+#[test]
+fn token_rewards_keep_separate_totals() {
+    let mut chain = RewardChain::new();
+    chain.add_rewards(U256::from(7), U256::from(100)).unwrap();
+    chain
+        .add_token_rewards([0x42; 20], U256::from(3), U256::from(101))
+        .unwrap();
+    chain
+        .add_token_rewards([0x42; 20], U256::from(4), U256::from(102))
+        .unwrap();
+    chain.add_rewards(U256::from(1), U256::from(103)).unwrap();
+    assert_eq!(
+        chain.add_token_rewards([0; 20], U256::from(5), U256::from(104)),
+        Err(SoarError::Reverted("Token rewards need a token address"))
+    );
+
+    let totals: Vec<_> = chain
+        .events
+        .iter()
+        .map(|event| (event.reward_token(), event.total_reward))
+        .collect();
+    assert_eq!(
+        totals,
+        vec![
+            ([0; 20], U256::from(7)),
+            ([0x42; 20], U256::from(3)),
+            ([0x42; 20], U256::from(7)),
+            ([0; 20], U256::from(8)),
+        ]
+    );
+    let mut previous = Bytes32::zero();
+    for event in chain.events.iter_mut() {
+        assert_eq!(event.previous_event_hash, previous);
+        assert!(event.verify_hash());
+        previous = event.current_event_hash;
+    }
+}
+
+#[test]
+fn relay_records_claim_intervals() {
+    let mut stakes = StakeChain::new();
+    let mut rewards = RewardChain::new();
+    stakes
+        .stake(ALICE, U256::from(10), U256::from(100))
+        .unwrap();
+    stakes.stake(BOB, U256::from(10), U256::from(101)).unwrap();
+    rewards
+        .add_rewards(U256::from(50), U256::from(102))
+        .unwrap();
+
+    rewards
+        .relay_snapshot(&stakes, ALICE, U256::from(103))
+        .unwrap();
+    let claim = rewards.rewards[&ALICE];
+    assert_eq!(
+        claim.stake_interval,
+        HashInterval {
+            start: Bytes32::zero(),
+            end: stakes.stake_chain
+        }
+    );
+    assert_eq!(claim.reward_interval.end, rewards.current_reward_chain);
+    assert_eq!(
+        claim.user_stake_interval.end,
+        stakes.events[0].current_event_hash
+    );
+    assert_eq!(claim.stake_snapshot_time, U256::from(101));
+    assert_eq!(claim.reward_snapshot_time, U256::from(103));
+    assert!(!claim.claimed);
+
+    // The contract only moves a claim forward once its stake interval has a start, which
+    // the first relay never sets, so a later relay starts at the beginning again.
+    rewards.claim_rewards(ALICE);
+    rewards
+        .add_rewards(U256::from(50), U256::from(104))
+        .unwrap();
+    rewards
+        .relay_snapshot(&stakes, ALICE, U256::from(105))
+        .unwrap();
+    let claim = rewards.rewards[&ALICE];
+    assert_eq!(claim.reward_interval.start, Bytes32::zero());
+    assert_eq!(claim.reward_interval.end, rewards.current_reward_chain);
+    assert!(claim.claimed);
+}
+
+#[test]
+fn simulated_chains_feed_the_calculator() {
+    let mut stakes = StakeChain::new();
+    let mut rewards = RewardChain::new();
+    stakes
+        .stake(ALICE, U256::from(30), U256::from(100))
+        .unwrap();
+    stakes.stake(BOB, U256::from(10), U256::from(100)).unwrap();
+    rewards
+        .add_rewards(U256::from(400), U256::from(101))
+        .unwrap();
+    stakes
+        .unstake(ALICE, U256::from(20), U256::from(102))
+        .unwrap();
+    rewards
+        .add_rewards(U256::from(200), U256::from(103))
+        .unwrap();
+
+    let builder = InputBuilder::new(stakes.events.clone(), rewards.events.clone());
+    let selection = SegmentSelection {
+        from_stake: None,
+        to_stake: EventSelector::Hash(stakes.stake_chain),
+        from_reward: None,
+        to_reward: EventSelector::Hash(rewards.current_reward_chain),
+    };
+    let mut alice = builder.build(ALICE, selection).unwrap();
+    let mut bob = builder.build(BOB, selection).unwrap();
+    // 3/4 of the first reward and 1/2 of the second.
+    assert_eq!(alice.calculate_reward().unwrap(), U256::from(400));
+    assert_eq!(bob.calculate_reward().unwrap(), U256::from(200));
+}
@@ -0,0 +1,70 @@
+use soar_lib::error::SoarError;
+use soar_lib::stake::{StakeChainExtendedEvent, StakeChainExtendedEventJSON};
+use soar_lib::types::U256;
+
+const STAKES: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/../data/stakes.json"));
+
+fn load_stakes() -> Vec<StakeChainExtendedEvent> {
+    let events: Vec<StakeChainExtendedEventJSON> = serde_json::from_str(STAKES).unwrap();
+    events
+        .into_iter()
+        .map(StakeChainExtendedEvent::try_from)
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn first_stake_event_matches_solidity() {
+    let mut event = load_stakes().remove(0);
+    assert_eq!(
+        hex::encode(event.hash()),
+        "7518a055ca3855cf42915e802a14b1bc6b3aea69849c7f94781350633f6be71b"
+    );
+}
+
+#[test]
+fn packed_encoding_layout() {
+    let event = load_stakes().remove(0);
+    let packed = event.encode_packed();
+    assert_eq!(packed.len(), soar_lib::stake::STAKE_EVENT_PACKED_LEN);
+    assert_eq!(&packed[0..20], &event.user);
+    assert_eq!(packed[20], 1);
+    assert_eq!(&packed[149..181], &event.previous_event_hash);
+}
+
+#[test]
+fn every_stake_event_verifies() {
+    for (index, mut event) in load_stakes().into_iter().enumerate() {
+        assert!(event.verify_hash(), "stake event {} hash mismatch", index);
+    }
+}
+
+#[test]
+fn unstake_events_verify() {
+    let unstakes: Vec<_> = load_stakes().into_iter().filter(|e| !e.is_stake).collect();
+    assert!(!unstakes.is_empty());
+    for mut event in unstakes {
+        assert!(event.verify_hash());
+    }
+}
+
+#[test]
+fn tampered_stake_event_fails() {
+    let mut event = load_stakes().remove(1);
+    event.total_user_stake += U256::one();
+    assert!(!event.verify_hash());
+}
+
+#[test]
+fn rejects_malformed_hex() {
+    let mut events: Vec<StakeChainExtendedEventJSON> = serde_json::from_str(STAKES).unwrap();
+    let mut event = events.remove(0);
+    event.current_event_hash = "0x1234".to_string();
+    assert_eq!(
+        StakeChainExtendedEvent::try_from(event).unwrap_err(),
+        SoarError::Parse {
+            field: "currentStakeChain",
+            value: "0x1234".to_string(),
+        }
+    );
+}
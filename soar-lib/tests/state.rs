@@ -0,0 +1,226 @@
+mod common;
+
+use common::load_input;
+use soar_lib::error::{Chain, Endpoint, SoarError};
+use soar_lib::reward_calculator::RewardCalculator;
+use soar_lib::state::{CalculatorState, ACCUMULATED_REWARD_PACKED_LEN, STATE_PACKED_LEN};
+use soar_lib::types::U256;
+
+/// A run continuing from `state` with the next `reward_events` reward events and every
+/// remaining stake event.
+fn next_run(state: &CalculatorState, reward_events: usize) -> RewardCalculator {
+    let mut calculator = load_input();
+    calculator.stake_events.drain(..state.stake_index);
+    calculator.reward_events.drain(..state.reward_index);
+    calculator.reward_events.truncate(reward_events);
+    calculator
+}
+
+/// Serializes and restores the state between runs, as when resuming on another machine.
+fn export(state: &CalculatorState) -> CalculatorState {
+    serde_json::from_str(&serde_json::to_string(state).unwrap()).unwrap()
+}
+
+#[test]
+fn single_run_matches_calculate_reward() {
+    let mut calculator = load_input();
+    let mut state = CalculatorState::new(&mut calculator).unwrap();
+    state.advance(&mut calculator, None).unwrap();
+    assert_eq!(
+        state.finish(&mut calculator),
+        load_input().calculate_token_rewards()
+    );
+}
+
+#[test]
+fn chunked_runs_match_calculate_reward() {
+    let expected = load_input().calculate_token_rewards().unwrap();
+    let reward_events = load_input().reward_events.len();
+    for chunk in 1..=reward_events {
+        let mut state = CalculatorState::new(&mut load_input()).unwrap();
+        while state.reward_index < reward_events
+            || state.stake_index < load_input().stake_events.len()
+        {
+            let mut calculator = next_run(&state, chunk);
+            state.advance(&mut calculator, None).unwrap();
+            state = export(&state);
+        }
+        assert_eq!(
+            state.finish(&mut load_input()),
+            Ok(expected.clone()),
+            "chunk {}",
+            chunk
+        );
+    }
+}
+
+#[test]
+fn stops_after_max_reward_events() {
+    let mut calculator = load_input();
+    let mut state = CalculatorState::new(&mut calculator).unwrap();
+    state.advance(&mut calculator, Some(3)).unwrap();
+    assert_eq!(state.reward_index, 3);
+    assert_eq!(
+        state.reward_hash,
+        calculator.reward_events[2].current_event_hash
+    );
+    let mut calculator = next_run(&state, usize::MAX);
+    state.advance(&mut calculator, None).unwrap();
+    assert_eq!(
+        state.finish(&mut calculator),
+        load_input().calculate_token_rewards()
+    );
+}
+
+#[test]
+fn rejects_run_without_enough_stake_events() {
+    let mut calculator = load_input();
+    calculator.stake_events.truncate(3);
+    let mut state = CalculatorState::new(&mut calculator).unwrap();
+    // Reward event 2 follows stake event 2 and might follow stake event 3 as well.
+    assert_eq!(
+        state.advance(&mut calculator, None),
+        Err(SoarError::StakeEventsExhausted { index: 2 })
+    );
+    assert_eq!(state.reward_index, 2);
+    assert_eq!(state.stake_index, 3);
+}
+
+#[test]
+fn rejects_stake_segment_starting_after_reward_events() {
+    let mut calculator = load_input();
+    calculator.claim.from_stake_event = Some(calculator.stake_events[1].clone());
+    calculator.stake_events.drain(..2);
+    let mut state = CalculatorState::new(&mut calculator).unwrap();
+    assert_eq!(
+        state.advance(&mut calculator, None),
+        Err(SoarError::RewardBeforeStakeSegment { index: 0 })
+    );
+}
+
+#[test]
+fn rejects_run_not_continuing_state() {
+    let mut calculator = load_input();
+    let mut state = CalculatorState::new(&mut calculator).unwrap();
+    state.advance(&mut calculator, Some(3)).unwrap();
+    let mut calculator = next_run(&state, usize::MAX);
+    calculator.reward_events.remove(0);
+    assert!(matches!(
+        state.advance(&mut calculator, None),
+        Err(SoarError::ChainLink {
+            chain: Chain::Reward,
+            index: 3,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn rejects_run_of_other_user() {
+    let mut calculator = load_input();
+    let mut state = CalculatorState::new(&mut calculator).unwrap();
+    calculator.user = calculator.stake_events[0].user;
+    assert!(matches!(
+        state.advance(&mut calculator, None),
+        Err(SoarError::UserMismatch { .. })
+    ));
+}
+
+#[test]
+fn rejects_finish_before_end_of_segment() {
+    let mut calculator = load_input();
+    let mut state = CalculatorState::new(&mut calculator).unwrap();
+    state.advance(&mut calculator, Some(3)).unwrap();
+    assert!(matches!(
+        state.finish(&mut calculator),
+        Err(SoarError::EndpointMismatch {
+            chain: Chain::Reward,
+            endpoint: Endpoint::To,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn digest_covers_every_field() {
+    let mut calculator = load_input();
+    let mut state = CalculatorState::new(&mut calculator).unwrap();
+    let start = state.digest();
+    assert_eq!(state.encode_packed().len(), STATE_PACKED_LEN);
+    assert_eq!(export(&state).digest(), start);
+    state.advance(&mut calculator, Some(1)).unwrap();
+    assert_eq!(
+        state.encode_packed().len(),
+        STATE_PACKED_LEN + ACCUMULATED_REWARD_PACKED_LEN
+    );
+    assert_ne!(state.digest(), start);
+    let mut altered = state.clone();
+    altered.accumulated_rewards[0].amount += U256::one();
+    assert_ne!(altered.digest(), state.digest());
+    altered = state.clone();
+    altered.accumulated_rewards[0].token = [0x42; 20];
+    assert_ne!(altered.digest(), state.digest());
+}
+
+#[test]
+fn chained_digests_reach_end_state() {
+    let mut calculator = load_input();
+    let claim_digest = calculator.claim_digest();
+    let mut state = CalculatorState::new(&mut calculator).unwrap();
+    let mut digests = vec![state.digest()];
+    while state.reward_index < calculator.reward_events.len() {
+        let mut run = next_run(&state, 4);
+        assert_eq!(run.claim_digest(), claim_digest);
+        state.advance(&mut run, None).unwrap();
+        digests.push(state.digest());
+    }
+    assert_eq!(digests.len(), 4);
+    assert_eq!(
+        state.finish(&mut calculator),
+        load_input().calculate_token_rewards()
+    );
+}
+
+#[test]
+fn accumulates_every_reward_token() {
+    let mut calculator = load_input();
+    let mut previous_hash = calculator.reward_events[0].previous_event_hash;
+    for (index, event) in calculator.reward_events.iter_mut().enumerate() {
+        if index % 3 == 2 {
+            event.token = Some([0x42; 20]);
+        }
+        event.previous_event_hash = previous_hash;
+        previous_hash = event.hash();
+        event.current_event_hash = previous_hash;
+    }
+    calculator.claim.to_reward_event = calculator.reward_events.last().unwrap().clone();
+    let expected = calculator.clone().calculate_token_rewards().unwrap();
+    assert_eq!(expected.len(), 2);
+
+    let mut state = CalculatorState::new(&mut calculator).unwrap();
+    for chunk in calculator.reward_events.chunks(4) {
+        let mut run = calculator.clone();
+        run.stake_events.drain(..state.stake_index);
+        run.reward_events = chunk.to_vec();
+        state.advance(&mut run, None).unwrap();
+        state = export(&state);
+    }
+    assert_eq!(state.finish(&mut calculator), Ok(expected));
+}
+
+#[test]
+fn runs_only_need_applied_stake_events_and_the_next() {
+    let mut state = CalculatorState::new(&mut load_input()).unwrap();
+    for chunk in [2, 3, 5] {
+        let start = state.clone();
+        let mut run = next_run(&start, chunk);
+        state.advance(&mut run, None).unwrap();
+        let mut trimmed = next_run(&start, chunk);
+        trimmed
+            .stake_events
+            .truncate(state.stake_index - start.stake_index + 1);
+        let mut trimmed_state = start;
+        trimmed_state.advance(&mut trimmed, None).unwrap();
+        assert_eq!(trimmed_state, state);
+    }
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use common::load_input;
+use soar_lib::error::SoarError;
+use soar_lib::reward_calculator::RewardCalculator;
+use soar_lib::rounding::RoundingPolicy;
+use soar_lib::types::{Address, U256};
+
+const TOKEN: Address = [0x42; 20];
+
+/// The input with every odd reward event distributing `TOKEN`, rehashing the reward chain.
+fn load_token_input() -> RewardCalculator {
+    let mut calculator = load_input();
+    let mut previous_hash = calculator.reward_events[0].previous_event_hash;
+    let mut token_total = U256::zero();
+    for (index, event) in calculator.reward_events.iter_mut().enumerate() {
+        if index % 2 == 1 {
+            token_total += event.amount;
+            event.token = Some(TOKEN);
+            event.total_reward = token_total;
+        }
+        event.previous_event_hash = previous_hash;
+        previous_hash = event.hash();
+        event.current_event_hash = previous_hash;
+    }
+    calculator.claim.to_reward_event = calculator.reward_events.last().unwrap().clone();
+    calculator
+}
+
+#[test]
+fn single_token_input_has_one_token_reward() {
+    let mut calculator = load_input();
+    let reward = calculator.calculate_reward().unwrap();
+    assert_eq!(
+        calculator.calculate_token_rewards().unwrap(),
+        vec![(Address::default(), reward)]
+    );
+}
+
+#[test]
+fn accumulates_reward_per_token() {
+    // Tokens do not change the stake at each reward event, so the amounts paid are those of
+    // the single token input.
+    let paid: Vec<U256> = load_input()
+        .calculate_distribution(RoundingPolicy::FloorPerEvent)
+        .unwrap()
+        .events
+        .into_iter()
+        .map(|event| event.paid)
+        .collect();
+    let token_reward = |parity: usize| {
+        paid.iter()
+            .enumerate()
+            .filter(|(index, _)| index % 2 == parity)
+            .fold(U256::zero(), |total, (_, paid)| total + *paid)
+    };
+
+    let mut calculator = load_token_input();
+    assert_eq!(
+        calculator.calculate_token_rewards().unwrap(),
+        vec![
+            (Address::default(), token_reward(0)),
+            (TOKEN, token_reward(1))
+        ]
+    );
+}
+
+#[test]
+fn rejects_single_reward_for_multiple_tokens() {
+    let mut calculator = load_token_input();
+    assert_eq!(
+        calculator.calculate_reward(),
+        Err(SoarError::MultipleRewardTokens)
+    );
+}
+
+#[test]
+fn rejects_stripped_token() {
+    // Copy the token chain onto freshly loaded events, whose hashes are not cached yet.
+    let token_calculator = load_token_input();
+    let mut calculator = load_input();
+    for (event, token_event) in calculator
+        .reward_events
+        .iter_mut()
+        .zip(&token_calculator.reward_events)
+    {
+        event.token = token_event.token;
+        event.total_reward = token_event.total_reward;
+        event.previous_event_hash = token_event.previous_event_hash;
+        event.current_event_hash = token_event.current_event_hash;
+    }
+    calculator.claim.to_reward_event = token_calculator.claim.to_reward_event;
+    calculator.reward_events[1].token = None;
+    assert!(matches!(
+        calculator.calculate_token_rewards(),
+        Err(SoarError::HashMismatch { index: 1, .. })
+    ));
+}
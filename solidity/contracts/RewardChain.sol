@@ -29,6 +29,8 @@ contract RewardChain {
 
     uint256 public totalRewards;
 
+    mapping(address => uint256) public totalTokenRewards;
+
     uint256 public rewardBeginTime;
 
     mapping(address => StakeRewardClaim) public rewards;
@@ -41,6 +43,15 @@ contract RewardChain {
         bytes32 currentRewardChain
     );
 
+    event TokenRewardsAdded(
+        address token,
+        uint256 amount,
+        uint256 totalRewards,
+        uint256 timestamp,
+        bytes32 previousRewardChain,
+        bytes32 currentRewardChain
+    );
+
     constructor(bytes32 startOfStakeChain) {
         // Initialize the beginning of the stake chain
         //beginningOfStakeChain = startOfStakeChain;
@@ -109,6 +120,32 @@ contract RewardChain {
         }
     }
 
+    function addTokenRewards(address token, uint256 amount) public {
+        // Should add rewards in another token than the reward token to the contract
+        require(token != address(0), "Token rewards need a token address");
+        totalTokenRewards[token] += amount;
+        bytes memory rewardData = abi.encodePacked(
+            token,
+            amount,
+            totalTokenRewards[token],
+            block.timestamp,
+            currentRewardChain
+        );
+        bytes32 previousRewardChain = currentRewardChain;
+        currentRewardChain = keccak256(rewardData);
+        emit TokenRewardsAdded(
+            token,
+            amount,
+            totalTokenRewards[token],
+            block.timestamp,
+            previousRewardChain,
+            currentRewardChain
+        );
+        if (rewardBeginTime == 0) {
+            rewardBeginTime = block.timestamp;
+        }
+    }
+
     /**
      * Users should be able to claim rewards by submitting a proof that the calculated reward values are correct for the user based on the snapshot of the stake chain.
      *
@@ -9,67 +9,6 @@ function solidityPackHash(types: string[], values: any[]) {
   return ethers.keccak256(packed);
 }
 
-function serializeToRust(key: any, value: any) {
-    //console.log(key, value);
-    if (key === "timestamp") {
-        return bigintToU256Array(value);
-    }
-    if (key === "amount") {
-        return bigintToU256Array(value);
-    }
-    if (key === "totalRewards") {
-        return bigintToU256Array(value);
-    }
-    if (key === "totalStaked") {
-        return bigintToU256Array(value);
-    }
-    if (key === "totalUserStake") {
-        return bigintToU256Array(value);
-    }
-    if (key === "previousStakeChain") {
-        return hexToByteArray(value.substring(2), 32);
-    }
-    if (key === "currentStakeChain") {
-        return hexToByteArray(value.substring(2), 32);
-    }
-    if (key === "previousRewardChain") {
-        return hexToByteArray(value.substring(2), 32);
-    }
-    if (key === "currentRewardChain") {
-        return hexToByteArray(value.substring(2), 32);
-    }
-    if (key === "user") {
-        return hexToByteArray(value.substring(2), 20);
-    }
-    if (key === "isStake") {
-        return value ? true : false;
-    }
-    return value;
-}
-
-// Helper function: Convert BigInt to U256 array representation (4 x 64-bit integers)
-function bigintToU256Array(value: any) {
-    const mask = BigInt("0xFFFFFFFFFFFFFFFF"); // Mask for 64 bits
-    const parts = [];
-    for (let i = 0; i < 4; i++) {
-        parts.push(parseInt((value & mask).toString())); // Extract 64 bits as a number
-        value >>= BigInt(64); // Shift right by 64 bits
-    }
-    return parts;
-}
-
-// Helper function: Convert hex string to a byte array of a fixed length
-function hexToByteArray(hex: any, length: any) {
-    const bytes = [];
-    for (let i = 0; i < hex.length; i += 2) {
-        bytes.push(parseInt(hex.substr(i, 2), 16));
-    }
-    if (bytes.length !== length) {
-        throw new Error(`Hex string does not match required length of ${length} bytes`);
-    }
-    return bytes;
-}
-
 let rewardChain: any;
 let owner: any;
 let addrs: any;
@@ -255,7 +194,9 @@ async function newDataset() {
                     })
                     lastStakeChainHash = newUserSnapshot[2];
                 }
-                if (Math.random() < 0.1 && toStakeChainEvent === undefined && fromStakeChainEvent === undefined) {
+                // The stake segment has to start before the reward segment, so that every
+                // reward event is shared out with a stake known from the segment.
+                if (Math.random() < 0.1 && toStakeChainEvent === undefined && fromStakeChainEvent === undefined && fromRewardChainEvent === undefined) {
                     fromStakeChainEvent = stakes[stakes.length - 1];
                     for (let j = stakes.length - 1;j>=0;j--) {
                         if (stakes[j].user === user) {
@@ -402,6 +343,21 @@ async function newDataset() {
         claim.toUserStakeChainEvent = toUserStakeChainEvent;
     }
 
+    // Stake events up to the start of the stake segment, back to the latest event of every
+    // staker holding stake at that point.
+    let stakeHistory: any[] = [];
+    if (fromStakeChainEvent !== undefined) {
+        const fromIndex = stakes.findIndex((stake) => stake.currentStakeChain === fromStakeChainEvent.currentStakeChain);
+        const latestIndex: any = {};
+        for (let j = 0; j<=fromIndex;j++) {
+            latestIndex[stakes[j].user] = j;
+        }
+        const starts = Object.values(latestIndex).filter((j: any) => stakes[j].totalUserStake > 0) as number[];
+        if (starts.length > 0) {
+            stakeHistory = stakes.slice(Math.min(...starts), fromIndex + 1);
+        }
+    }
+
     const json = {
         user: user,
         stake_events: stakes.filter((stake) => {
@@ -418,12 +374,11 @@ async function newDataset() {
                 return true;
             }
         }),
-        claim: claim
+        claim: claim,
+        stakeHistory: stakeHistory
     }
-    fs.writeFileSync('./data/input-plain.json', JSON.stringify(json, (k, v) => { if (typeof v === "bigint") return v.toString(); return v; }, 2));
-    //console.log("=====================================");
-    const input = JSON.stringify(json, serializeToRust);
-    //console.log(JSON.stringify(json, serializeToRust));
+    // RewardCalculator accepts decimal amounts and hex hashes directly.
+    const input = JSON.stringify(json, (k, v) => { if (typeof v === "bigint") return v.toString(); return v; }, 2);
     //console.log("================================");
     //console.log("Expected rewards", expectedRewards.toString());
     const expected = {